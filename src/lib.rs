@@ -5,8 +5,12 @@
 extern crate rand;
 extern crate num;
 extern crate num_iter;
+#[cfg(feature = "mmap")]
+extern crate memmap2;
 
-pub mod data_struct;
+pub mod matrix;
+pub mod slice;
+pub mod vector_data;
 
 
 
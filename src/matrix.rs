@@ -1,15 +1,24 @@
 // Basic data structs
 use std::fmt;
-use std::fmt::{Debug, Display};
+use std::fmt::{Debug, Display, LowerExp};
 
 use std::ops::Range;
 
+use std::convert::{TryFrom, TryInto};
+
 use std::marker::PhantomData;
 
+use std::error;
+use std::io;
+use std::io::{Read, Write};
+use std::io::{BufReader, BufWriter};
+use std::fs::File;
+use std::path::Path;
+
 use num::Float;
 use num::traits::cast::FromPrimitive;
 
-use super::vector_data::Vector;
+use super::vector_data::{total_cmp, Vector};
 
 //=============================================================================
 //Matrix major axis
@@ -21,16 +30,33 @@ pub enum Axis {
     Row,
 }
 
-impl Axis {
-    fn transpose(&self) -> Axis {
+
+//=============================================================================
+//Errors
+//=============================================================================
+/// Errors from fallible matrix operations
+#[derive(Debug)]
+pub enum MatrixError {
+    Io(io::Error),
+    Parse(String),
+    Shape(String),
+}
+
+impl fmt::Display for MatrixError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Column => Axis::Row,
-            Row => Axis::Column,
+            MatrixError::Io(e) => write!(f, "I/O error: {}", e),
+            MatrixError::Parse(s) => write!(f, "Parse error: {}", s),
+            MatrixError::Shape(s) => write!(f, "Shape error: {}", s),
         }
     }
+}
 
-    fn t(&self) -> Axis {
-        self.transpose()
+impl error::Error for MatrixError {}
+
+impl From<io::Error> for MatrixError {
+    fn from(e: io::Error) -> MatrixError {
+        MatrixError::Io(e)
     }
 }
 
@@ -70,8 +96,8 @@ impl Axis {
 //Matrix
 //=============================================================================
 /// Matrix struct
-#[derive(Debug, Clone, Eq, PartialEq)]
-pub struct Matrix<'a, T: Float> {
+#[derive(Debug, Clone)]
+pub struct Matrix<'a, T> {
     data: Vector<T>,
     rows: usize, // number of rows
     cols: usize, // number of columns
@@ -81,7 +107,7 @@ pub struct Matrix<'a, T: Float> {
 
 }
 
-impl<'a, T: Float> Matrix<'a, T> {
+impl<'a, T: Copy> Matrix<'a, T> {
 
     pub fn get_rows(&self) -> usize {
         self.rows
@@ -118,107 +144,148 @@ impl<'a, T: Float> Matrix<'a, T> {
         self.data.get_mut_data()
     }
 
+    /// Contiguous backing buffer. The element order follows the storage
+    /// mode: rows one after another for `Axis::Row`, columns for
+    /// `Axis::Column`.
+    pub fn as_slice(&self) -> &[T] {
+        self.data.as_slice()
+    }
+
+    /// Mutable contiguous backing buffer, laid out as for `as_slice`
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        self.data.as_mut_slice()
+    }
+
+    /// Whether the elements fill the backing buffer with no gaps, so that
+    /// `as_slice` holds exactly the matrix in storage order
+    pub fn is_contiguous(&self) -> bool {
+        let lead = match self.mode {
+            Axis::Row => self.cols,
+            Axis::Column => self.rows,
+        };
+        (self.strd == lead || self.get_size() == 0) && self.data.get_size() == self.get_size()
+    }
+
     /// Get storage mode
     pub fn get_mode(&self) -> Axis {
         self.mode.clone()
     }
 
+    /// Get the element without bounds checking
+    ///
+    /// # Safety
+    ///
+    /// `idx[0]` must be less than the row count and `idx[1]` less than the
+    /// column count.
+    pub unsafe fn uget(&self, idx: [usize;2]) -> &T {
+        &*(self.data.as_ptr().add(self.uidx(idx[0], idx[1])))
+    }
+
     /// get the element id without bounds checking
+    ///
+    /// # Safety
+    ///
+    /// `idx[0]` must be less than the row count and `idx[1]` less than the
+    /// column count.
     pub unsafe fn uget_mut(&mut self, idx: [usize;2]) -> &mut T {
-        &mut *(self.data.as_mut_ptr().offset((idx[0] * self.strd + idx[1]) as isize))
+        let i = self.uidx(idx[0], idx[1]);
+        &mut *(self.data.as_mut_ptr().add(i))
+    }
+
+    /// Value at `(r, c)` without bounds checking in release builds; debug
+    /// builds assert the indices are in bounds.
+    ///
+    /// # Safety
+    ///
+    /// `r` must be less than the row count and `c` less than the column
+    /// count.
+    #[inline]
+    pub unsafe fn get_unchecked(&self, r: usize, c: usize) -> T {
+        debug_assert!(r < self.rows && c < self.cols, "Index out of bounds");
+        *self.uget([r, c])
+    }
+
+    /// Mutable reference to the element at `(r, c)`, checked as for
+    /// `get_unchecked`
+    ///
+    /// # Safety
+    ///
+    /// `r` must be less than the row count and `c` less than the column
+    /// count.
+    #[inline]
+    pub unsafe fn get_unchecked_mut(&mut self, r: usize, c: usize) -> &mut T {
+        debug_assert!(r < self.rows && c < self.cols, "Index out of bounds");
+        self.uget_mut([r, c])
     }
 
-    /// Get reference to an element without bounds  checking
-    pub fn get_elem_ref(&mut self, id: [usize; 2]) -> Option<&T> {
+    /// Get reference to an element with bounds checking
+    pub fn get_ref(&self, id: [usize; 2]) -> Option<&T> {
         let rid = id[0];
         let cid = id[1];
 
         if rid >= self.get_rows() || cid >= self.get_cols() {
             None
         } else {
-            unsafe { Some(self.uget_mut(id)) }
+            unsafe { Some(self.uget(id)) }
         }
     }
 
+    /// Get reference to an element with bounds checking
+    pub fn get_elem_ref(&self, id: [usize; 2]) -> Option<&T> {
+        self.get_ref(id)
+    }
+
     /// Get specified column unchecked
 //    unsafe fn ucol(&self, id: usize) -> Column<T> {
 //        let pt = self.as_ptr().offset(id as isize);
 //        Column { col: 
 
 
-    /// Transpose of a matrix
-    pub fn transpose(&mut self) -> Matrix<'a, T> 
+    /// Transpose of a matrix, copied into fresh storage with the same
+    /// storage mode
+    pub fn transpose(&self) -> Matrix<'a, T> 
         where T: Copy + Float {
-            match self.get_mode() {
-                Column => {
-                    let mut _data = Vec::with_capacity(self.get_cols() * 
-                                                       self.get_rows());
-
-                    unsafe {
-                        _data.set_len(self.get_cols() * self.get_rows());
-
-                        for i in 0..self.get_cols() {
-                            for j in 0..self.get_rows() {
-                                *_data.get_unchecked_mut(i * self.get_rows() + j) =
-                                    *self.uget_mut([j,i]);
-                            }
-                        }
-                    }
-                    Matrix {
-                        data: Vector { data: _data.to_vec() },
-                        rows: self.get_cols(),
-                        cols: self.get_rows(),
-                        strd: self.get_rows(),
-                        mode: self.mode.t(),
-                        mark: PhantomData::<&'a T>,
+            let (rows, cols) = (self.get_cols(), self.get_rows());
+            let mut dat = Vec::with_capacity(rows * cols);
+            match self.mode {
+                Axis::Row => for i in 0..rows {
+                    for j in 0..cols {
+                        dat.push(self.get(j, i).unwrap());
                     }
                 },
-
-                Row => {
-                    let mut _data = Vec::with_capacity(self.get_cols() * 
-                                                       self.get_rows());
-
-                    unsafe {
-                        _data.set_len(self.get_cols() * self.get_rows());
-
-                        for i in 0..self.get_rows() {
-                            for j in 0..self.get_cols() {
-                                *_data.get_unchecked_mut(i * self.get_cols() + j) =
-                                    *self.uget_mut([j,i]);
-                            }
-                        }
-                    }
-                    Matrix {
-                        data: Vector { data:_data.to_vec() },
-                        rows: self.get_cols(),
-                        cols: self.get_rows(),
-                        strd: self.get_cols(),
-                        mode: self.mode.t(),
-                        mark: PhantomData::<&'a T>,
+                Axis::Column => for j in 0..cols {
+                    for i in 0..rows {
+                        dat.push(self.get(j, i).unwrap());
                     }
-                }
+                },
+            }
+            Matrix {
+                data: Vector { data: dat, },
+                rows,
+                cols,
+                strd: match self.mode { Axis::Row => cols, Axis::Column => rows },
+                mode: self.get_mode(),
+                mark: PhantomData::<&'a T>,
             }
         }
 
     /// Get the index for the specified row and column ids
     #[inline]
     pub fn index(&self, rid: usize, cid: usize) -> Option<usize> {
-        match self.mode {
-            Axis::Column => {
-                let (r, c) = (rid, cid);
-                self.tridx(c * self.get_rows() + r)
-            },
-            Axis::Row => {
-                let (r, c) = (cid, rid);
-                Some(c * self.get_rows() + r)
-            }
+        if rid >= self.get_rows() || cid >= self.get_cols() {
+            None
+        } else {
+            Some(self.uidx(rid, cid))
         }
     }
     
+    /// Storage offset of an element without bounds checking
     #[inline]
-    fn tridx(&self, id: usize) -> Option<usize> {
-        let i = (id % self.get_cols()) * self.get_rows() + (id / self.get_cols()) as usize;
-        Some(i)
+    fn uidx(&self, rid: usize, cid: usize) -> usize {
+        match self.mode {
+            Axis::Column => cid * self.strd + rid,
+            Axis::Row => rid * self.strd + cid,
+        }
     }
 
     /// Set the value at the specified location
@@ -236,12 +303,22 @@ impl<'a, T: Float> Matrix<'a, T> {
 
     /// Get the value from the specified location
     pub fn get(&self, rid: usize, cid: usize) -> Option<T> {
-        let i = self.index(rid, cid);
-        let vals = self.data.as_slice();
-//        assert!(i < vals.len(), "Index out of bounds");
         match self.index(rid, cid) {
             Some(i) => { self.data.as_slice().get(i).map(|&n| n) },
-            None => { panic!("Index out of bound") },
+            None => None,
+        }
+    }
+
+    /// Get the value at a `(row, col)` position
+    pub fn at(&self, idx: (usize, usize)) -> Option<T> {
+        self.index(idx.0, idx.1).map(|i| self.data.as_slice()[i])
+    }
+
+    /// Set the value at a `(row, col)` position
+    pub fn set_at(&mut self, idx: (usize, usize), val: T) {
+        match self.index(idx.0, idx.1) {
+            Some(i) => { self.data.as_mut_slice()[i] = val },
+            None => { panic!("Index out of bounds") },
         }
     }
 
@@ -259,9 +336,17 @@ impl<'a, T: Float> Matrix<'a, T> {
         }
     }
 
+    /// Matrix whose columns are consecutive runs of `v`, the inverse of
+    /// `vectorize`
+    pub fn from_vec_col(v: &Vector<T>, rows: usize, cols: usize) -> Matrix<'a, T> {
+        assert!(rows * cols == v.get_size(), "Dimension mismatch");
+        let dat = v.as_slice();
+        Matrix::from_fn(rows, cols, |i, j| dat[j * rows + i])
+    }
+
     /// Matrix from function
-    pub fn from_fn<F>(rows: usize, cols: usize, f: F) -> Matrix<'a, T> 
-        where F: Fn(usize, usize) -> T {
+    pub fn from_fn<F>(rows: usize, cols: usize, mut f: F) -> Matrix<'a, T> 
+        where F: FnMut(usize, usize) -> T {
             let mut dat = Vec::with_capacity(rows * cols);
             for i in 0..rows {
                 for j in 0..cols {
@@ -279,6 +364,11 @@ impl<'a, T: Float> Matrix<'a, T> {
             }
         }
 
+    /// Matrix with no rows and no columns
+    pub fn empty() -> Matrix<'a, T> {
+        Matrix::from_vec(Vec::new(), 0, 0)
+    }
+
     /// Matrix with all 1's
     pub fn unit(rows: usize, cols: usize) -> Matrix<'a, T> 
         where T: Float {
@@ -306,7 +396,8 @@ impl<'a, T: Float> Matrix<'a, T> {
         }
 
     /// Diagonal matrix
-    pub fn diag(vec: &Vec<T>, rows: usize, cols: usize) -> Matrix<'a, T> {
+    pub fn diag(vec: &Vec<T>, rows: usize, cols: usize) -> Matrix<'a, T> 
+        where T: Float {
         let n = vec.len();
         let mut mat = Matrix {
             data: Vector { data: vec![T::zero(); n * n], },
@@ -328,157 +419,3992 @@ impl<'a, T: Float> Matrix<'a, T> {
         where T: Float {
             Matrix::diag(&vec![T::one(); dim], dim, dim)
         }
-}
 
+    /// Block diagonal matrix with the given blocks along the diagonal
+    pub fn block_diag(mats: &[Matrix<'a, T>]) -> Matrix<'a, T> 
+        where T: Float {
+        let rows = mats.iter().map(|m| m.get_rows()).sum();
+        let cols = mats.iter().map(|m| m.get_cols()).sum();
+        let mut out = Matrix::zero(rows, cols);
 
-    ///Print the matrix
-    impl<'a, T: Float + Display + Debug> fmt::Display for Matrix<'a, T>
-    {
-        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-            for i in 0..self.get_rows() {
-                for j in 0..self.get_cols() {
-                    write!(f, "{:1.5} ", self.get(i, j).unwrap());
+        let (mut r0, mut c0) = (0, 0);
+        for m in mats {
+            for i in 0..m.get_rows() {
+                for j in 0..m.get_cols() {
+                    out.set(r0 + i, c0 + j, m.get(i, j).unwrap());
                 }
-                write!(f, "\n")?;
             }
-            Ok(())
+            r0 += m.get_rows();
+            c0 += m.get_cols();
         }
+        out
     }
 
+    /// Matrix from a vector of equally long rows
+    pub fn from_rows(rows: Vec<Vec<T>>) -> Matrix<'a, T> {
+        let nr = rows.len();
+        let nc = rows.first().map_or(0, |r| r.len());
+        assert!(rows.iter().all(|r| r.len() == nc), "Rows differ in length");
+        Matrix::from_vec(rows.into_iter().flatten().collect(), nr, nc)
+    }
 
+    /// The columns stacked into one vector, the `vec` operator
+    pub fn vectorize(&self) -> Vector<T> {
+        let r = self.get_rows();
+        Vector::from_fn(|k| self.get(k % r, k / r).unwrap(), self.get_size())
+    }
 
+    /// The rows of the matrix, in logical order
+    pub fn to_rows(&self) -> Vec<Vec<T>> {
+        (0..self.get_rows())
+            .map(|i| (0..self.get_cols()).map(|j| self.get(i, j).unwrap()).collect())
+            .collect()
+    }
+}
 
+/// Matrix from borrowed rows; ragged rows are an error
+impl<'a, 's, T: Copy> TryFrom<&'s [&'s [T]]> for Matrix<'a, T> {
+    type Error = MatrixError;
 
-//=============================================================================
-//Matrix Slice
-//=============================================================================
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
-pub struct MatrixSlice<'a, T> {
-    pt: *const T,
-    nr: usize,
-    nc: usize,
-    rs: usize,
-    _m: PhantomData<&'a T>,
+    fn try_from(rows: &'s [&'s [T]]) -> Result<Matrix<'a, T>, MatrixError> {
+        let nc = rows.first().map_or(0, |r| r.len());
+        if let Some(i) = rows.iter().position(|r| r.len() != nc) {
+            return Err(MatrixError::Shape(format!(
+                "row {} has length {}, expected {}", i, rows[i].len(), nc)));
+        }
+        Ok(Matrix::from_vec(rows.concat(), rows.len(), nc))
+    }
 }
 
-//=============================================================================
-//Mutable Matrix Slice
-//=============================================================================
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
-pub struct MatrixMutSlice<'a, T> {
-    pt: *mut T,
-    nr: usize,
-    nc: usize,
-    rs: usize,
-    _m: PhantomData<&'a T>,
-}
 
-//=============================================================================
-//Immutable Row slice from matrix 
-//=============================================================================
-#[derive(Debug, Clone, Copy)]
-pub struct Row<'a, T> {
-    row: MatrixSlice<'a, T>,
+/// Matrices are equal when they have the same shape and the same elements,
+/// whatever their storage mode
+impl<'a, T: Copy + PartialEq> PartialEq for Matrix<'a, T> {
+    fn eq(&self, other: &Matrix<'a, T>) -> bool {
+        self.get_shape() == other.get_shape() &&
+            (0..self.get_rows()).all(|i| (0..self.get_cols()).all(|j| {
+                self.get(i, j) == other.get(i, j)
+            }))
+    }
 }
 
+impl<'a, T: Copy + Eq> Eq for Matrix<'a, T> {}
+
+
 //=============================================================================
-//Mutable Row Slice from matrix
+//Matrix comparison
 //=============================================================================
-#[derive(Debug, Clone, Copy)]
-pub struct RowMut<'a, T> {
-    row: MatrixMutSlice<'a, T>,
+impl<'a, T: Float> Matrix<'a, T> {
+
+    /// Elementwise equality within an absolute tolerance
+    pub fn approx_eq(&self, other: &Matrix<'a, T>, tol: T) -> bool {
+        self.all_pairs(other, |a, b| (a - b).abs() <= tol)
+    }
+
+    /// Elementwise equality within a relative tolerance:
+    /// |a - b| <= rel_tol * max(|a|, |b|)
+    pub fn approx_eq_rel(&self, other: &Matrix<'a, T>, rel_tol: T) -> bool {
+        self.all_pairs(other, |a, b| {
+            a == b || (a - b).abs() <= rel_tol * a.abs().max(b.abs())
+        })
+    }
+
+    /// Check a predicate over each pair of elements of equally shaped matrices
+    fn all_pairs<F>(&self, other: &Matrix<'a, T>, f: F) -> bool
+        where F: Fn(T, T) -> bool {
+            if self.get_shape() != other.get_shape() {
+                return false;
+            }
+            for i in 0..self.get_rows() {
+                for j in 0..self.get_cols() {
+                    if !f(self.get(i, j).unwrap(), other.get(i, j).unwrap()) {
+                        return false;
+                    }
+                }
+            }
+            true
+        }
+
+    /// Mask of ones where elements are close, `|a - b| <= atol + rtol |b|`
+    /// as in NumPy's `isclose`, and zeros elsewhere
+    pub fn is_close(&self, other: &Matrix<'a, T>, atol: T, rtol: T) -> Matrix<'a, T> {
+        self.zip_elems(other, |a, b| {
+            indicator(a == b || (a - b).abs() <= atol + rtol * b.abs())
+        })
+    }
 }
 
-//=============================================================================
-//Immutable Row Iter
-//=============================================================================
+
+/// Matrix wrapper whose `==` is elementwise equality within the embedded
+/// absolute tolerance, e.g. `assert_eq!(Close(&a, tol), Close(&b, tol))`.
+/// When the two tolerances differ the smaller one is used.
 #[derive(Debug, Clone, Copy)]
-pub struct RowsIter<'a, T> {
-    start_pos: *const T,
-    row_pos: usize,
-    row_slice: usize,
-    col_slice: usize,
-    row_stride: usize,
-    _markr: PhantomData<&'a T>,
+pub struct Close<'m, T: Float>(pub &'m Matrix<'m, T>, pub T);
+
+impl<'m, T: Float> PartialEq for Close<'m, T> {
+    fn eq(&self, other: &Close<'m, T>) -> bool {
+        self.0.approx_eq(other.0, self.1.min(other.1))
+    }
 }
 
+
 //=============================================================================
-//Mutable Row Iter
+//Elementwise operations
 //=============================================================================
-#[derive(Debug, Clone, Copy)]
-pub struct RowsMutIter<'a, T> {
-    start_pos: *mut T,
-    row_pos: usize,
-    row_slice: usize,
-    col_slice: usize,
-    row_stride: usize,
-    _markr: PhantomData<&'a T>,
+impl<'a, T: Float> Matrix<'a, T> {
+
+    /// Elementwise division; division by zero follows IEEE rules
+    pub fn hadamard_div(&self, rhs: &Matrix<'a, T>) -> Matrix<'a, T> {
+        self.zip_elems(rhs, |a, b| a / b)
+    }
+
+    /// Elementwise exponential
+    pub fn exp(&self) -> Matrix<'a, T> {
+        self.map_elems(|x| x.exp())
+    }
+
+    /// Elementwise natural logarithm
+    pub fn ln(&self) -> Matrix<'a, T> {
+        self.map_elems(|x| x.ln())
+    }
+
+    /// Elementwise square root
+    pub fn sqrt(&self) -> Matrix<'a, T> {
+        self.map_elems(|x| x.sqrt())
+    }
+
+    /// Elementwise sine
+    pub fn sin(&self) -> Matrix<'a, T> {
+        self.map_elems(|x| x.sin())
+    }
+
+    /// Elementwise cosine
+    pub fn cos(&self) -> Matrix<'a, T> {
+        self.map_elems(|x| x.cos())
+    }
+
+    /// Elementwise hyperbolic tangent
+    pub fn tanh(&self) -> Matrix<'a, T> {
+        self.map_elems(|x| x.tanh())
+    }
+
+    /// Elementwise reciprocal; zero maps to an infinity of the same sign
+    pub fn recip(&self) -> Matrix<'a, T> {
+        self.map_elems(|x| x.recip())
+    }
+
+    /// Raise every element to the power `exp`
+    pub fn powf(&self, exp: T) -> Matrix<'a, T> {
+        self.map_elems(|x| x.powf(exp))
+    }
+
+    /// Raise every element to the integer power `n`
+    pub fn powi(&self, n: i32) -> Matrix<'a, T> {
+        self.map_elems(|x| x.powi(n))
+    }
+
+    /// Limit every element to `[min, max]`; NaN elements stay NaN
+    pub fn clamp(&self, min: T, max: T) -> Matrix<'a, T> {
+        assert!(min <= max, "min must not exceed max");
+        self.map_elems(|x| if x < min { min } else if x > max { max } else { x })
+    }
+
+    /// Limit every element to `[min, max]` in place; NaN elements stay NaN
+    pub fn clamp_mut(&mut self, min: T, max: T) {
+        assert!(min <= max, "min must not exceed max");
+        for x in self.data.iter_mut() {
+            if *x < min {
+                *x = min;
+            } else if *x > max {
+                *x = max;
+            }
+        }
+    }
+
+    /// Apply `f(row, col, value)` to every element
+    pub fn apply_indexed<F>(&self, f: F) -> Matrix<'a, T>
+        where F: Fn(usize, usize, T) -> T {
+            Matrix::from_fn(self.get_rows(), self.get_cols(), |i, j| {
+                f(i, j, self.get(i, j).unwrap())
+            })
+        }
+
+    /// Elementwise absolute value
+    pub fn abs(&self) -> Matrix<'a, T> {
+        self.map_elems(|x| x.abs())
+    }
+
+    /// Elementwise sign
+    pub fn signum(&self) -> Matrix<'a, T> {
+        self.map_elems(|x| x.signum())
+    }
+
+    /// Round each element to the nearest integer, half away from zero
+    pub fn round(&self) -> Matrix<'a, T> {
+        self.map_elems(|x| x.round())
+    }
+
+    /// Elementwise floor
+    pub fn floor(&self) -> Matrix<'a, T> {
+        self.map_elems(|x| x.floor())
+    }
+
+    /// Elementwise ceiling
+    pub fn ceil(&self) -> Matrix<'a, T> {
+        self.map_elems(|x| x.ceil())
+    }
+
+    /// Apply `f(element, scalar)` to every element
+    pub fn apply_scalar<F: Fn(T, T) -> T>(&self, scalar: T, f: F) -> Matrix<'a, T> {
+        self.map_elems(|x| f(x, scalar))
+    }
+
+    /// Mask with ones where `self > rhs` and zeros elsewhere
+    pub fn greater_than(&self, rhs: &Matrix<'a, T>) -> Matrix<'a, T> {
+        self.zip_elems(rhs, |a, b| indicator(a > b))
+    }
+
+    /// Mask with ones where `self < rhs` and zeros elsewhere
+    pub fn less_than(&self, rhs: &Matrix<'a, T>) -> Matrix<'a, T> {
+        self.zip_elems(rhs, |a, b| indicator(a < b))
+    }
+
+    /// Mask with ones where `self == rhs` and zeros elsewhere
+    pub fn equal_to(&self, rhs: &Matrix<'a, T>) -> Matrix<'a, T> {
+        self.zip_elems(rhs, |a, b| indicator(a == b))
+    }
+
+    /// Complement of a mask: ones where the element is zero, zeros elsewhere
+    pub fn logical_not(&self) -> Matrix<'a, T> {
+        self.map_elems(|x| indicator(x == T::zero()))
+    }
+
+    /// Rescale to a Frobenius norm of `max_norm` if the norm exceeds it,
+    /// otherwise return the matrix unchanged
+    pub fn clip_by_norm(&self, max_norm: T) -> Matrix<'a, T> {
+        let norm = self.frobenius_norm();
+        if norm > max_norm {
+            self.map_elems(|x| x * (max_norm / norm))
+        } else {
+            self.clone()
+        }
+    }
+
+    /// Scale so that the elements sum to `target`. Panics if the current
+    /// sum is zero.
+    pub fn rescale_to_sum(&self, target: T) -> Matrix<'a, T> {
+        let total = self.sum();
+        assert!(total != T::zero(), "Sum is zero");
+        self.map_elems(|x| x / total * target)
+    }
+
+    /// Nearest symmetric matrix `(A + A^T) / 2`
+    pub fn symmetrize(&self) -> Matrix<'a, T> {
+        assert!(self.get_rows() == self.get_cols(), "Matrix is not square");
+        let two = T::one() + T::one();
+        self.zip_elems(&self.transpose(), |a, b| (a + b) / two)
+    }
+
+    /// Skew-symmetric part `(A - A^T) / 2`
+    pub fn skew(&self) -> Matrix<'a, T> {
+        assert!(self.get_rows() == self.get_cols(), "Matrix is not square");
+        let two = T::one() + T::one();
+        self.zip_elems(&self.transpose(), |a, b| (a - b) / two)
+    }
+
+    /// Apply `f` to every element
+    fn map_elems<F>(&self, f: F) -> Matrix<'a, T>
+        where F: Fn(T) -> T {
+            Matrix::from_fn(self.get_rows(), self.get_cols(), |i, j| {
+                f(self.get(i, j).unwrap())
+            })
+        }
+
+    /// Combine equally shaped matrices element by element
+    fn zip_elems<F>(&self, rhs: &Matrix<'a, T>, f: F) -> Matrix<'a, T>
+        where F: Fn(T, T) -> T {
+            assert!(self.get_shape() == rhs.get_shape(), "Shapes do not match");
+            Matrix::from_fn(self.get_rows(), self.get_cols(), |i, j| {
+                f(self.get(i, j).unwrap(), rhs.get(i, j).unwrap())
+            })
+        }
 }
 
+
+
 //=============================================================================
-//Immutable Column slice from matrix
+//Selection
 //=============================================================================
-#[derive(Debug, Clone, Copy)]
-pub struct Col<'a, T> {
-    col: MatrixSlice<'a, T>,
+impl<'a, T: Float> Matrix<'a, T> {
+
+    /// Coordinates of the elements satisfying `pred`, in row-major order
+    pub fn find<F: Fn(T) -> bool>(&self, pred: F) -> Vec<(usize, usize)> {
+        let mut out = Vec::new();
+        for i in 0..self.get_rows() {
+            for j in 0..self.get_cols() {
+                if pred(self.get(i, j).unwrap()) {
+                    out.push((i, j));
+                }
+            }
+        }
+        out
+    }
+
+    /// Elements where the equally shaped `mask` is nonzero, in row-major
+    /// order
+    pub fn select_mask(&self, mask: &Matrix<'a, T>) -> Vector<T> {
+        assert!(self.get_shape() == mask.get_shape(), "Shapes do not match");
+        let data = mask.find(|x| x != T::zero()).into_iter()
+            .map(|(i, j)| self.get(i, j).unwrap())
+            .collect();
+        Vector::new(data)
+    }
+
+    /// Whether any element satisfies `pred`; stops at the first match
+    pub fn any<F: Fn(T) -> bool>(&self, pred: F) -> bool {
+        self.data.iter().any(|&x| pred(x))
+    }
+
+    /// Whether every element satisfies `pred`; stops at the first failure
+    pub fn all<F: Fn(T) -> bool>(&self, pred: F) -> bool {
+        self.data.iter().all(|&x| pred(x))
+    }
+
+    /// Whether every element is finite (neither NaN nor infinite)
+    pub fn is_finite(&self) -> bool {
+        self.all(|x| x.is_finite())
+    }
+
+    /// `k x 2` matrix whose rows are the `(row, col)` coordinates of the
+    /// elements satisfying `pred`, in row-major order
+    pub fn argwhere<F: Fn(T) -> bool>(&self, pred: F) -> Matrix<'a, usize> {
+        let found = self.find(pred);
+        let k = found.len();
+        let data = found.into_iter().flat_map(|(i, j)| vec![i, j]).collect();
+        Matrix::from_vec(data, k, 2)
+    }
 }
 
 //=============================================================================
-//Mutable column slice from matrix
+//Reductions
 //=============================================================================
-#[derive(Debug, Clone, Copy)]
-pub struct ColMut<'a, T> {
-    col: MatrixMutSlice<'a, T>,
+impl<'a, T: Float> Matrix<'a, T> {
+
+    /// Sum of all elements
+    pub fn sum(&self) -> T {
+        self.data.iter().fold(T::zero(), |acc, &x| acc + x)
+    }
+
+    /// Product of all elements
+    pub fn product(&self) -> T {
+        self.data.iter().fold(T::one(), |acc, &x| acc * x)
+    }
+
+    /// Smallest and largest element, found in a single pass, or `None`
+    /// for an empty matrix
+    pub fn minmax(&self) -> Option<(T, T)> {
+        let first = *self.data.get_data().first()?;
+        Some(self.data.iter().fold((first, first), |(lo, hi), &x| (lo.min(x), hi.max(x))))
+    }
+
+    /// Number of elements with absolute value above `tol`
+    pub fn count_nonzero(&self, tol: T) -> usize {
+        self.data.iter().filter(|x| x.abs() > tol).count()
+    }
+
+    /// Frobenius norm: square root of the sum of squared elements
+    pub fn frobenius_norm(&self) -> T {
+        self.data.iter().fold(T::zero(), |acc, &x| acc + x * x).sqrt()
+    }
+
+    /// Counts of elements in `bins` equal-width bins over `range`. The last
+    /// bin includes the upper end. Values outside the range go to the end
+    /// bins when `clamp` is set and are skipped otherwise; NaN is always
+    /// skipped.
+    pub fn histogram(&self, bins: usize, range: (T, T), clamp: bool) -> Vec<usize> {
+        let (lo, hi) = range;
+        assert!(bins > 0, "Bin count must be nonzero");
+        assert!(lo < hi, "Empty histogram range");
+        let nb = T::from(bins).unwrap();
+        let mut counts = vec![0; bins];
+
+        for &x in self.data.iter() {
+            if x.is_nan() || (!clamp && (x < lo || x > hi)) {
+                continue;
+            }
+            let b = ((x - lo) / (hi - lo) * nb).floor().max(T::zero()).min(nb - T::one());
+            counts[b.to_usize().unwrap()] += 1;
+        }
+        counts
+    }
+
+    /// The `q`-th percentile (`q` from 0 to 100) of all elements, linearly
+    /// interpolated between the two nearest ranks. NaN elements are skipped.
+    pub fn percentile(&self, q: T) -> T {
+        assert!(q >= T::zero() && q <= T::from(100).unwrap(), "Percentile must be in [0, 100]");
+        let mut v: Vec<T> = self.data.iter().cloned().filter(|x| !x.is_nan()).collect();
+        assert!(!v.is_empty(), "Matrix has no non-NaN elements");
+        v.sort_by(|&a, &b| total_cmp(a, b));
+
+        let rank = q / T::from(100).unwrap() * T::from(v.len() - 1).unwrap();
+        let lo = rank.floor().to_usize().unwrap();
+        let hi = rank.ceil().to_usize().unwrap();
+        v[lo] + (v[hi] - v[lo]) * (rank - rank.floor())
+    }
 }
 
 //=============================================================================
-//Immutable column iter
+//Operations along an axis
 //=============================================================================
-#[derive(Debug, Clone, Copy)]
-pub struct ColIter<'a, T> {
-    start_pos: *const T,
-    col_pos: usize,
-    row_slice: usize,
-    col_slice: usize,
-    col_stride: usize,
-    _markr: PhantomData<&'a T>,
+impl<'a, T: Float> Matrix<'a, T> {
+
+    /// Numerically stable softmax over each row (`Axis::Row`) or each
+    /// column (`Axis::Column`)
+    pub fn softmax_axis(&self, axis: Axis) -> Matrix<'a, T> {
+        self.map_lanes(axis, |lane| {
+            let m = lane.iter().fold(T::neg_infinity(), |x, &y| x.max(y));
+            let e: Vec<T> = lane.iter().map(|&x| (x - m).exp()).collect();
+            let s = e.iter().fold(T::zero(), |acc, &x| acc + x);
+            e.into_iter().map(|x| x / s).collect()
+        })
+    }
+
+    /// Running sum along each row (`Axis::Row`) or column (`Axis::Column`)
+    pub fn cumsum_axis(&self, axis: Axis) -> Matrix<'a, T> {
+        self.map_lanes(axis, |lane| {
+            lane.iter().scan(T::zero(), |acc, &x| {
+                *acc = *acc + x;
+                Some(*acc)
+            }).collect()
+        })
+    }
+
+    /// Summed-area table: element (i, j) is the sum of every element in
+    /// rows `0..=i` and columns `0..=j`
+    pub fn integral_image(&self) -> Matrix<'a, T> {
+        self.cumsum_axis(Axis::Row).cumsum_axis(Axis::Column)
+    }
+
+    /// Consecutive differences along each row (`Axis::Row`) or column
+    /// (`Axis::Column`); that dimension shrinks by one
+    pub fn diff_axis(&self, axis: Axis) -> Matrix<'a, T> {
+        self.map_lanes(axis, |lane| {
+            lane.windows(2).map(|w| w[1] - w[0]).collect()
+        })
+    }
+
+    /// Finite-difference derivative along each row (`Axis::Row`) or column
+    /// (`Axis::Column`), with unit spacing: central differences inside and
+    /// one-sided differences at the two ends
+    pub fn gradient(&self, axis: Axis) -> Matrix<'a, T> {
+        let two = T::one() + T::one();
+        self.map_lanes(axis, |lane| {
+            let n = lane.len();
+            (0..n).map(|k| match k {
+                _ if n < 2 => T::zero(),
+                0 => lane[1] - lane[0],
+                _ if k == n - 1 => lane[k] - lane[k - 1],
+                _ => (lane[k + 1] - lane[k - 1]) / two,
+            }).collect()
+        })
+    }
+
+    /// Mean of each row
+    pub fn row_means(&self) -> Vector<T> {
+        Vector::from_fn(|i| mean(&self.lane(&Axis::Row, i)), self.get_rows())
+    }
+
+    /// Mean of each column
+    pub fn col_means(&self) -> Vector<T> {
+        Vector::from_fn(|j| mean(&self.lane(&Axis::Column, j)), self.get_cols())
+    }
+
+    /// Weighted average of the rows, `sum_i w_i row_i / sum_i w_i`
+    pub fn weighted_row_mean(&self, weights: &Vector<T>) -> Vector<T> {
+        assert!(weights.get_size() == self.get_rows(), "Dimension mismatch");
+        let w = weights.as_slice();
+        let total = w.iter().fold(T::zero(), |acc, &x| acc + x);
+        Vector::from_fn(|j| dot(w, &self.lane(&Axis::Column, j)) / total, self.get_cols())
+    }
+
+    /// Column index of the maximum element of each row, the first one on
+    /// ties. NaN is ignored and a row holding only NaN gives 0. Panics if
+    /// the matrix has no columns.
+    pub fn argmax_rows(&self) -> Vec<usize> {
+        assert!(self.get_cols() > 0, "Matrix has no columns");
+        (0..self.get_rows())
+            .map(|i| Vector::new(self.lane(&Axis::Row, i)).argmax().unwrap_or(0))
+            .collect()
+    }
+
+    /// Column indices of the `k` largest elements of each row, largest
+    /// first; ties keep the lower column index first and NaN ranks last
+    pub fn topk_rows(&self, k: usize) -> Matrix<'a, usize> {
+        assert!(k <= self.get_cols(), "k exceeds the number of columns");
+        let data = (0..self.get_rows()).flat_map(|i| {
+            let row = self.lane(&Axis::Row, i);
+            let mut order: Vec<usize> = (0..row.len()).collect();
+            order.sort_by(|&a, &b| match (row[a].is_nan(), row[b].is_nan()) {
+                (false, false) => total_cmp(row[b], row[a]),
+                _ => total_cmp(row[a], row[b]),
+            });
+            order.truncate(k);
+            order
+        }).collect();
+        Matrix::from_vec(data, self.get_rows(), k)
+    }
+
+    /// Apply `f` to every row (`Axis::Row`) or column (`Axis::Column`) and
+    /// assemble the results, which must all have the same length
+    fn map_lanes<F>(&self, axis: Axis, f: F) -> Matrix<'a, T>
+        where F: Fn(&[T]) -> Vec<T> {
+            let (nl, len) = match axis {
+                Axis::Row => (self.get_rows(), self.get_cols()),
+                Axis::Column => (self.get_cols(), self.get_rows()),
+            };
+            let lanes: Vec<Vec<T>> = (0..nl)
+                .map(|l| f(&self.lane(&axis, l)))
+                .collect();
+            let out = match lanes.first() {
+                Some(l) => l.len(),
+                None => f(&vec![T::zero(); len]).len(),
+            };
+
+            match axis {
+                Axis::Row => Matrix::from_fn(nl, out, |i, j| lanes[i][j]),
+                Axis::Column => Matrix::from_fn(out, nl, |i, j| lanes[j][i]),
+            }
+        }
+
+    /// Copy of row `l` (`Axis::Row`) or column `l` (`Axis::Column`)
+    fn lane(&self, axis: &Axis, l: usize) -> Vec<T> {
+        match axis {
+            Axis::Row => (0..self.get_cols())
+                .map(|k| self.get(l, k).unwrap()).collect(),
+            Axis::Column => (0..self.get_rows())
+                .map(|k| self.get(k, l).unwrap()).collect(),
+        }
+    }
 }
 
+
 //=============================================================================
-//Mutable column iter
+//Diagonals
 //=============================================================================
-#[derive(Debug, Clone, Copy)]
-pub struct ColMutIter<'a, T> {
-    start_pos: *mut T,
-    col_pos: usize,
-    row_slice: usize,
-    col_slice: usize,
-    col_stride: usize,
-    _markr: PhantomData<&'a T>,
+impl<'a, T: Float> Matrix<'a, T> {
+
+    /// Anti-diagonal of a square matrix: elements (i, n - 1 - i)
+    pub fn anti_diagonal(&self) -> Vector<T> {
+        assert!(self.get_rows() == self.get_cols(), "Matrix is not square");
+        let n = self.get_rows();
+        Vector::from_fn(|i| self.get(i, n - 1 - i).unwrap(), n)
+    }
+
+    /// Main diagonal
+    pub fn diagonal(&self) -> Vector<T> {
+        self.diagonal_k(0)
+    }
+
+    /// The `k`-th diagonal: above the main diagonal for positive `k`, below
+    /// it for negative `k`
+    pub fn diagonal_k(&self, k: isize) -> Vector<T> {
+        let (r0, c0) = if k >= 0 { (0, k as usize) } else { ((-k) as usize, 0) };
+        let n = self.get_rows().saturating_sub(r0)
+            .min(self.get_cols().saturating_sub(c0));
+        Vector::from_fn(|i| self.get(r0 + i, c0 + i).unwrap(), n)
+    }
+
+    /// Set every main diagonal element to `val`
+    pub fn fill_diagonal(&mut self, val: T) {
+        for i in 0..self.mindim() {
+            self.set(i, i, val);
+        }
+    }
+
+    /// Add `val` to every main diagonal element, e.g. `A + lambda I`
+    pub fn add_to_diagonal(&mut self, val: T) {
+        for i in 0..self.mindim() {
+            let d = self.get(i, i).unwrap();
+            self.set(i, i, d + val);
+        }
+    }
+
+    /// Sum of the main diagonal
+    pub fn trace(&self) -> T {
+        self.diagonal().iter().fold(T::zero(), |acc, &x| acc + x)
+    }
+
+    /// Matrix divided by its trace, so the result has trace one. Panics if
+    /// the trace is zero.
+    pub fn normalize_trace(&self) -> Matrix<'a, T> {
+        assert!(self.get_rows() == self.get_cols(), "Matrix is not square");
+        let tr = self.trace();
+        assert!(tr != T::zero(), "Trace is zero");
+        self.map_elems(|x| x / tr)
+    }
+
+    /// Lower triangle, including the main diagonal; zeros above it
+    pub fn tril(&self) -> Matrix<'a, T> {
+        let mut m = Matrix::zero(self.get_rows(), self.get_cols());
+        for i in 0..self.get_rows() {
+            for j in 0..self.get_cols().min(i + 1) {
+                m.set(i, j, self.get(i, j).unwrap());
+            }
+        }
+        m
+    }
 }
 
+
 //=============================================================================
-//Iterate over slice data immutably
+//Sub-matrices
 //=============================================================================
-#[derive(Debug, Clone, Copy)]
-pub struct SliceIter<'a, T> {
-    slice: *const T,
-    row_pos: usize,
-    col_pos: usize,
-    row_slice: usize,
-    col_slice: usize,
-    _markr: PhantomData<&'a T>,
+impl<'a, T: Float> Matrix<'a, T> {
+
+    /// Copy of every `row_step`-th row and `col_step`-th column
+    pub fn step_view(&self, row_step: usize, col_step: usize) -> Matrix<'a, T> {
+        assert!(row_step > 0 && col_step > 0, "Step must be nonzero");
+        let nr = (self.get_rows() + row_step - 1) / row_step;
+        let nc = (self.get_cols() + col_step - 1) / col_step;
+        Matrix::from_fn(nr, nc, |i, j| {
+            self.get(i * row_step, j * col_step).unwrap()
+        })
+    }
+
+    /// Copy with the rows ordered by the values in column `col`. The sort
+    /// is stable, so tied rows keep their relative order, and rows with a
+    /// NaN key go last in either direction.
+    pub fn sort_by_column(&self, col: usize, ascending: bool) -> Matrix<'a, T> {
+        assert!(col < self.get_cols(), "Column index out of bounds");
+        let key = self.lane(&Axis::Column, col);
+        let mut order: Vec<usize> = (0..self.get_rows()).collect();
+        order.sort_by(|&a, &b| match (key[a].is_nan(), key[b].is_nan()) {
+            (false, false) if !ascending => total_cmp(key[b], key[a]),
+            _ => total_cmp(key[a], key[b]),
+        });
+        Matrix::from_fn(self.get_rows(), self.get_cols(), |i, j| {
+            self.get(order[i], j).unwrap()
+        })
+    }
+
+    /// Iterate over consecutive blocks of at most `n` rows; the final
+    /// block may be smaller
+    pub fn row_chunks(&self, n: usize) -> impl Iterator<Item = Matrix<'a, T>> + '_ {
+        assert!(n > 0, "Chunk size must be nonzero");
+        (0..self.get_rows()).step_by(n).map(move |start| {
+            let len = n.min(self.get_rows() - start);
+            Matrix::from_fn(len, self.get_cols(), |i, j| {
+                self.get(start + i, j).unwrap()
+            })
+        })
+    }
+
+    /// Repeat every element into an `r` by `c` block, e.g. to upsample an
+    /// image
+    pub fn repeat(&self, r: usize, c: usize) -> Matrix<'a, T> {
+        Matrix::from_fn(self.get_rows() * r, self.get_cols() * c, |i, j| {
+            self.get(i / r, j / c).unwrap()
+        })
+    }
+
+    /// Mean of each non-overlapping `pool_h` by `pool_w` block. Panics if
+    /// the block shape does not divide the matrix shape.
+    pub fn avg_pool(&self, pool_h: usize, pool_w: usize) -> Matrix<'a, T> {
+        self.pool(pool_h, pool_w, mean)
+    }
+
+    /// Maximum of each non-overlapping `pool_h` by `pool_w` block. Panics
+    /// if the block shape does not divide the matrix shape.
+    pub fn max_pool(&self, pool_h: usize, pool_w: usize) -> Matrix<'a, T> {
+        self.pool(pool_h, pool_w, |b| {
+            b.iter().fold(T::neg_infinity(), |x, &y| x.max(y))
+        })
+    }
+
+    /// Reduce each non-overlapping `pool_h` by `pool_w` block with `f`
+    fn pool<F>(&self, pool_h: usize, pool_w: usize, f: F) -> Matrix<'a, T>
+        where F: Fn(&[T]) -> T {
+            assert!(pool_h > 0 && pool_w > 0, "Pool size must be nonzero");
+            assert!(self.get_rows() % pool_h == 0 && self.get_cols() % pool_w == 0,
+                    "Pool size does not divide the matrix shape");
+            Matrix::from_fn(self.get_rows() / pool_h, self.get_cols() / pool_w, |i, j| {
+                let block: Vec<T> = (0..pool_h * pool_w)
+                    .map(|k| self.get(i * pool_h + k / pool_w, j * pool_w + k % pool_w).unwrap())
+                    .collect();
+                f(&block)
+            })
+        }
 }
 
+
 //=============================================================================
-//Iterate over slice data mutably
+//Matrix products
 //=============================================================================
-#[derive(Debug, Clone, Copy)]
-pub struct SliceMutIter<'a, T> {
-    slice: *mut T,
-    row_pos: usize,
-    col_pos: usize,
-    row_slice: usize,
-    col_slice: usize,
-    _markr: PhantomData<&'a T>,
+impl<'a, T: Float> Matrix<'a, T> {
+
+    /// Matrix product
+    pub fn matmul(&self, rhs: &Matrix<'a, T>) -> Matrix<'a, T> {
+        self.tensordot(rhs, Axis::Column, Axis::Row)
+    }
+
+    /// Contract `self` and `rhs` over the chosen dimensions. Contracting
+    /// columns of `self` against rows of `rhs` is the ordinary product;
+    /// other combinations contract against the transposes.
+    pub fn tensordot(&self, rhs: &Matrix<'a, T>, self_axis: Axis,
+                     rhs_axis: Axis) -> Matrix<'a, T> {
+        let (m, k) = match self_axis {
+            Axis::Column => (self.get_rows(), self.get_cols()),
+            Axis::Row => (self.get_cols(), self.get_rows()),
+        };
+        let (k2, n) = match rhs_axis {
+            Axis::Row => (rhs.get_rows(), rhs.get_cols()),
+            Axis::Column => (rhs.get_cols(), rhs.get_rows()),
+        };
+        assert!(k == k2, "Contracted dimensions do not match");
+
+        let a = |i: usize, l: usize| match self_axis {
+            Axis::Column => self.get(i, l).unwrap(),
+            Axis::Row => self.get(l, i).unwrap(),
+        };
+        let b = |l: usize, j: usize| match rhs_axis {
+            Axis::Row => rhs.get(l, j).unwrap(),
+            Axis::Column => rhs.get(j, l).unwrap(),
+        };
+        Matrix::from_fn(m, n, |i, j| {
+            (0..k).fold(T::zero(), |acc, l| acc + a(i, l) * b(l, j))
+        })
+    }
+
+    /// Integer matrix power; negative exponents raise the inverse and
+    /// return `None` if the matrix is singular
+    pub fn pow_signed(&self, n: isize) -> Option<Matrix<'a, T>> {
+        assert!(self.get_rows() == self.get_cols(), "Matrix is not square");
+        let mut base = if n < 0 { self.inverse()? } else { self.clone() };
+        let mut acc = Matrix::eye(self.get_rows());
+        let mut e = n.unsigned_abs();
+
+        while e > 0 {
+            if e & 1 == 1 {
+                acc = acc.matmul(&base);
+            }
+            e >>= 1;
+            if e > 0 {
+                base = base.matmul(&base);
+            }
+        }
+        Some(acc)
+    }
+
+    /// In-place rank-one update `A += alpha x y^T`
+    pub fn rank1_update(&mut self, alpha: T, x: &Vector<T>, y: &Vector<T>) {
+        assert!(x.get_size() == self.get_rows() && y.get_size() == self.get_cols(),
+                "Dimension mismatch");
+        for (i, &xi) in x.iter().enumerate() {
+            for (j, &yj) in y.iter().enumerate() {
+                let v = self.get(i, j).unwrap() + alpha * xi * yj;
+                self.set(i, j, v);
+            }
+        }
+    }
+
+    /// Gram matrix `A^T A`; only the upper triangle is computed and then
+    /// mirrored
+    pub fn gram(&self) -> Matrix<'a, T> {
+        let n = self.get_cols();
+        let cols: Vec<Vec<T>> = (0..n).map(|j| self.lane(&Axis::Column, j)).collect();
+        let mut g = Matrix::zero(n, n);
+        for i in 0..n {
+            for j in i..n {
+                let v = dot(&cols[i], &cols[j]);
+                g.set(i, j, v);
+                g.set(j, i, v);
+            }
+        }
+        g
+    }
+
+    /// Matrix-vector product `A x`
+    pub fn matvec(&self, x: &Vector<T>) -> Vector<T> {
+        Vector::new(self.mul_slice(x.as_slice()))
+    }
+
+    /// Kronecker product: block `(i, j)` is `a_ij B`
+    pub fn kron(&self, rhs: &Matrix<'a, T>) -> Matrix<'a, T> {
+        let (p, q) = rhs.get_shape();
+        Matrix::from_fn(self.get_rows() * p, self.get_cols() * q, |i, j| {
+            self.get(i / p, j / q).unwrap() * rhs.get(i % p, j % q).unwrap()
+        })
+    }
+
+    /// Kronecker sum `A (+) B = A (x) I + I (x) B` of square matrices
+    pub fn kron_sum(&self, rhs: &Matrix<'a, T>) -> Matrix<'a, T> {
+        assert!(self.get_rows() == self.get_cols() && rhs.get_rows() == rhs.get_cols(),
+                "Matrix is not square");
+        let left = self.kron(&Matrix::eye(rhs.get_rows()));
+        let right = Matrix::eye(self.get_rows()).kron(rhs);
+        left.zip_elems(&right, |x, y| x + y)
+    }
+}
+
+
+//=============================================================================
+//Decompositions
+//=============================================================================
+impl<'a, T: Float> Matrix<'a, T> {
+
+    /// Sign of the determinant: +1, -1, or 0 for a singular matrix
+    pub fn det_sign(&self) -> i32 {
+        match self.lu_factor() {
+            None => 0,
+            Some((lu, _, swaps)) => {
+                let neg = (0..lu.get_rows())
+                    .filter(|&i| lu.get(i, i).unwrap() < T::zero())
+                    .count();
+                if (swaps + neg) % 2 == 0 { 1 } else { -1 }
+            }
+        }
+    }
+
+    /// Determinant of a square matrix, zero if it is singular
+    pub fn det(&self) -> T {
+        match self.lu_factor() {
+            None => T::zero(),
+            Some((lu, _, swaps)) => {
+                let d = lu.diagonal().iter().fold(T::one(), |acc, &x| acc * x);
+                if swaps % 2 == 0 { d } else { -d }
+            }
+        }
+    }
+
+    /// Determinant of a triangular matrix as the product of its diagonal.
+    /// Panics unless the matrix is upper or lower triangular up to rounding.
+    pub fn det_triangular(&self) -> T {
+        assert!(self.get_rows() == self.get_cols(), "Matrix is not square");
+        let n = self.get_rows();
+        let scale = self.get_data().iter().fold(T::zero(), |m, &x| m.max(x.abs()));
+        let tol = scale * T::epsilon() * T::from(n).unwrap();
+        let small = |i: usize, j: usize| self.get(i, j).unwrap().abs() <= tol;
+        let upper = (0..n).all(|i| (0..i).all(|j| small(i, j)));
+        let lower = (0..n).all(|i| (i + 1..n).all(|j| small(i, j)));
+        assert!(upper || lower, "Matrix is not triangular");
+        self.diagonal().iter().fold(T::one(), |acc, &x| acc * x)
+    }
+
+    /// LU factorization with partial pivoting, `P A = L U`. Returns `L`
+    /// (unit diagonal, stored below the diagonal) and `U` packed into one
+    /// matrix, the row permutation and the number of row swaps, or `None`
+    /// if the matrix is singular.
+    fn lu_factor(&self) -> Option<(Matrix<'a, T>, Vec<usize>, usize)> {
+        assert!(self.get_rows() == self.get_cols(), "Matrix is not square");
+        let n = self.get_rows();
+        let mut a = Matrix::from_fn(n, n, |i, j| self.get(i, j).unwrap());
+        let mut perm: Vec<usize> = (0..n).collect();
+        let mut swaps = 0;
+
+        let scale = a.get_data().iter().fold(T::zero(), |m, &x| m.max(x.abs()));
+        let tol = scale * T::epsilon() * T::from(n).unwrap();
+
+        for k in 0..n {
+            let p = (k..n).fold(k, |p, i| {
+                if a.get(i, k).unwrap().abs() > a.get(p, k).unwrap().abs() { i } else { p }
+            });
+            if a.get(p, k).unwrap().abs() <= tol {
+                return None;
+            }
+            if p != k {
+                for j in 0..n {
+                    let t = a.get(k, j).unwrap();
+                    a.set(k, j, a.get(p, j).unwrap());
+                    a.set(p, j, t);
+                }
+                perm.swap(k, p);
+                swaps += 1;
+            }
+
+            let pivot = a.get(k, k).unwrap();
+            for i in k + 1..n {
+                let l = a.get(i, k).unwrap() / pivot;
+                a.set(i, k, l);
+                for j in k + 1..n {
+                    let v = a.get(i, j).unwrap() - l * a.get(k, j).unwrap();
+                    a.set(i, j, v);
+                }
+            }
+        }
+        Some((a, perm, swaps))
+    }
+
+    /// Cholesky factor: lower triangular `L` with `A = L L^T`, or `None`
+    /// if the matrix is not symmetric positive definite. Only the lower
+    /// triangle of `A` is read.
+    pub fn cholesky(&self) -> Option<Matrix<'a, T>> {
+        assert!(self.get_rows() == self.get_cols(), "Matrix is not square");
+        let n = self.get_rows();
+        let mut l = Matrix::zero(n, n);
+        for j in 0..n {
+            let lj = l.lane(&Axis::Row, j);
+            let d = self.get(j, j).unwrap() - dot(&lj[..j], &lj[..j]);
+            if d.is_nan() || d <= T::zero() {
+                return None;
+            }
+            let d = d.sqrt();
+            l.set(j, j, d);
+            for i in j + 1..n {
+                let li = l.lane(&Axis::Row, i);
+                l.set(i, j, (self.get(i, j).unwrap() - dot(&li[..j], &lj[..j])) / d);
+            }
+        }
+        Some(l)
+    }
+
+    /// Inverse of a square matrix, or `None` if it is singular
+    pub fn inverse(&self) -> Option<Matrix<'a, T>> {
+        let (lu, perm, _) = self.lu_factor()?;
+        let n = self.get_rows();
+        let cols: Vec<Vec<T>> = (0..n).map(|j| {
+            let e: Vec<T> = (0..n)
+                .map(|i| if i == j { T::one() } else { T::zero() })
+                .collect();
+            lu_solve(&lu, &perm, &e)
+        }).collect();
+        Some(Matrix::from_fn(n, n, |i, j| cols[j][i]))
+    }
+
+    /// Solution of `A x = b` by LU factorization, or `None` if `A` is
+    /// singular
+    pub fn solve(&self, b: &Vector<T>) -> Option<Vector<T>> {
+        assert!(b.get_size() == self.get_rows(), "Dimension mismatch");
+        let (lu, perm, _) = self.lu_factor()?;
+        Some(Vector::new(lu_solve(&lu, &perm, b.as_slice())))
+    }
+
+    /// Solve a tridiagonal system in O(n) with the Thomas algorithm. `sub`
+    /// and `sup` hold the `n - 1` entries below and above the diagonal.
+    /// There is no pivoting, so the system should be diagonally dominant
+    /// or otherwise stable without it.
+    pub fn solve_tridiagonal(sub: &Vector<T>, diag: &Vector<T>, sup: &Vector<T>,
+                             b: &Vector<T>) -> Vector<T> {
+        let n = diag.get_size();
+        assert!(b.get_size() == n, "Dimension mismatch");
+        assert!(sub.get_size() + 1 == n.max(1) && sup.get_size() + 1 == n.max(1),
+                "Off-diagonals must have one element fewer than the diagonal");
+        let (a, d, c) = (sub.as_slice(), diag.as_slice(), sup.as_slice());
+        let mut cp = vec![T::zero(); n];
+        let mut x = b.into_vec();
+
+        for i in 0..n {
+            let (lower, prev_c) = if i == 0 { (T::zero(), T::zero()) } else { (a[i - 1], cp[i - 1]) };
+            let m = d[i] - lower * prev_c;
+            if i + 1 < n {
+                cp[i] = c[i] / m;
+            }
+            let prev_x = if i == 0 { T::zero() } else { x[i - 1] };
+            x[i] = (x[i] - lower * prev_x) / m;
+        }
+        for i in (0..n.saturating_sub(1)).rev() {
+            x[i] = x[i] - cp[i] * x[i + 1];
+        }
+        Vector::new(x)
+    }
+
+    /// Solution `X` of the Sylvester equation `A X + X B = C`, through the
+    /// vectorized system `(I (x) A + B^T (x) I) vec(X) = vec(C)`. Panics if
+    /// `A` and `-B` share an eigenvalue, when there is no unique solution.
+    pub fn solve_sylvester(a: &Matrix<'a, T>, b: &Matrix<'a, T>, c: &Matrix<'a, T>)
+        -> Matrix<'a, T> {
+            let (m, n) = c.get_shape();
+            assert!(a.get_shape() == (m, m) && b.get_shape() == (n, n), "Dimension mismatch");
+            let k = Matrix::eye(n).kron(a)
+                .zip_elems(&b.transpose().kron(&Matrix::eye(m)), |x, y| x + y);
+            let x = k.solve(&c.vectorize()).expect("Sylvester equation has no unique solution");
+            Matrix::from_vec_col(&x, m, n)
+        }
+
+    /// Real Schur decomposition `A = Q T Q^T` with `Q` orthogonal and `T`
+    /// quasi-upper-triangular: complex conjugate eigenvalue pairs remain
+    /// as 2x2 blocks on the diagonal. Uses the Francis double shift QR
+    /// algorithm on the Hessenberg form. Panics if that does not converge
+    /// within `30 n` iterations per eigenvalue, as for a matrix with NaN
+    /// elements.
+    pub fn schur(&self) -> (Matrix<'a, T>, Matrix<'a, T>) {
+        assert!(self.get_rows() == self.get_cols(), "Matrix is not square");
+        let n = self.get_rows();
+        let (mut h, mut q) = self.hessenberg_parts();
+        let eps = T::epsilon();
+        let norm = h.iter().flatten().fold(T::zero(), |m, &x| m.max(x.abs()));
+
+        let mut hi = n.saturating_sub(1);
+        let mut iter = 0;
+        while hi > 0 {
+            // Look for a negligible subdiagonal element to split at
+            let mut l = hi;
+            while l > 0 {
+                let mut s = h[l - 1][l - 1].abs() + h[l][l].abs();
+                if s == T::zero() {
+                    s = norm;
+                }
+                if h[l][l - 1].abs() <= eps * s {
+                    h[l][l - 1] = T::zero();
+                    break;
+                }
+                l -= 1;
+            }
+
+            if l == hi {
+                hi -= 1;
+                iter = 0;
+                continue;
+            }
+            if l + 1 == hi {
+                split_2x2(&mut h, &mut q, l);
+                if hi < 2 {
+                    break;
+                }
+                hi -= 2;
+                iter = 0;
+                continue;
+            }
+
+            iter += 1;
+            assert!(iter <= 30 * n, "Schur iteration did not converge");
+
+            // Double shift from the trailing 2x2 block, with an ad hoc
+            // shift every tenth iteration to break cycles
+            let m = hi - 1;
+            let (s, t) = if iter % 10 == 0 {
+                let ex = h[hi][m].abs() + h[m][m - 1].abs();
+                let d = h[hi][hi];
+                let c = T::from(1.5).unwrap();
+                (d + d + c * ex, d * d + c * ex * d + ex * ex)
+            } else {
+                (h[m][m] + h[hi][hi], h[m][m] * h[hi][hi] - h[m][hi] * h[hi][m])
+            };
+
+            let mut x = h[l][l] * h[l][l] + h[l][l + 1] * h[l + 1][l]
+                - s * h[l][l] + t;
+            let mut y = h[l + 1][l] * (h[l][l] + h[l + 1][l + 1] - s);
+            let mut z = h[l + 1][l] * h[l + 2][l + 1];
+            for k in l..hi - 1 {
+                let v = householder_vector(&[x, y, z]);
+                let c0 = if k > l { k - 1 } else { l };
+                reflect_rows(&mut h, &v, k, c0..n);
+                reflect_cols(&mut h, &v, k, 0..(k + 4).min(hi + 1));
+                reflect_cols(&mut q, &v, k, 0..n);
+                if k > l {
+                    h[k + 1][k - 1] = T::zero();
+                    h[k + 2][k - 1] = T::zero();
+                }
+
+                x = h[k + 1][k];
+                y = h[k + 2][k];
+                if k + 3 <= hi {
+                    z = h[k + 3][k];
+                }
+            }
+            let v = householder_vector(&[x, y]);
+            reflect_rows(&mut h, &v, hi - 1, hi - 2..n);
+            reflect_cols(&mut h, &v, hi - 1, 0..hi + 1);
+            reflect_cols(&mut q, &v, hi - 1, 0..n);
+            h[hi][hi - 2] = T::zero();
+        }
+
+        (Matrix::from_rows(q), Matrix::from_rows(h))
+    }
+
+    /// Householder reduction to upper Hessenberg form `A = Q H Q^T`,
+    /// returning the rows of `H` and `Q`
+    fn hessenberg_parts(&self) -> (Vec<Vec<T>>, Vec<Vec<T>>) {
+        let n = self.get_rows();
+        let mut h = self.to_rows();
+        let mut q = Matrix::eye(n).to_rows();
+
+        for k in 0..n.saturating_sub(2) {
+            let x: Vec<T> = (k + 1..n).map(|i| h[i][k]).collect();
+            let v = householder_vector(&x);
+            reflect_rows(&mut h, &v, k + 1, k..n);
+            reflect_cols(&mut h, &v, k + 1, 0..n);
+            reflect_cols(&mut q, &v, k + 1, 0..n);
+            for row in h.iter_mut().skip(k + 2) {
+                row[k] = T::zero();
+            }
+        }
+        (h, q)
+    }
+
+    /// Householder bidiagonalization `A = U B V^T` with `U` and `V`
+    /// orthogonal and `B` upper bidiagonal, the first phase of a
+    /// Golub-Kahan SVD
+    pub fn bidiagonalize(&self) -> (Matrix<'a, T>, Matrix<'a, T>, Matrix<'a, T>) {
+        let (m, n) = self.get_shape();
+        let mut b = self.to_rows();
+        let mut u = Matrix::eye(m).to_rows();
+        let mut v = Matrix::eye(n).to_rows();
+
+        for k in 0..m.min(n) {
+            let x: Vec<T> = (k..m).map(|i| b[i][k]).collect();
+            let h = householder_vector(&x);
+            reflect_rows(&mut b, &h, k, k..n);
+            reflect_cols(&mut u, &h, k, 0..m);
+            for row in b.iter_mut().skip(k + 1) {
+                row[k] = T::zero();
+            }
+
+            if k + 1 < n {
+                let h = householder_vector(&b[k][k + 1..]);
+                reflect_cols(&mut b, &h, k + 1, k..m);
+                reflect_cols(&mut v, &h, k + 1, 0..n);
+                for x in b[k].iter_mut().skip(k + 2) {
+                    *x = T::zero();
+                }
+            }
+        }
+
+        (Matrix::from_rows(u), Matrix::from_rows(b), Matrix::from_rows(v))
+    }
+}
+
+
+
+/// Solve `A x = b` given the packed LU factors and row permutation of `A`
+fn lu_solve<T: Float>(lu: &Matrix<T>, perm: &[usize], b: &[T]) -> Vec<T> {
+    let n = lu.get_rows();
+    let mut x: Vec<T> = perm.iter().map(|&p| b[p]).collect();
+
+    for i in 0..n {
+        for j in 0..i {
+            x[i] = x[i] - lu.get(i, j).unwrap() * x[j];
+        }
+    }
+    for i in (0..n).rev() {
+        for j in i + 1..n {
+            x[i] = x[i] - lu.get(i, j).unwrap() * x[j];
+        }
+        x[i] = x[i] / lu.get(i, i).unwrap();
+    }
+    x
+}
+
+
+//=============================================================================
+//Orthogonal transforms
+//=============================================================================
+impl<'a, T: Float> Matrix<'a, T> {
+
+    /// Householder reflector `H = I - 2 v v^T / (v^T v)` mapping `x` onto a
+    /// multiple of `e_1`. A zero `x` gives the identity.
+    pub fn householder(x: &Vector<T>) -> Matrix<'a, T> {
+        let n = x.get_size();
+        let v = householder_vector(x.as_slice());
+        let vv = dot(&v, &v);
+        if vv == T::zero() {
+            return Matrix::eye(n);
+        }
+        let two = T::one() + T::one();
+        Matrix::from_fn(n, n, |i, j| {
+            let d = if i == j { T::one() } else { T::zero() };
+            d - two * v[i] * v[j] / vv
+        })
+    }
+
+    /// Givens rotation `(c, s)` such that `c a + s b = r` and
+    /// `-s a + c b = 0`
+    pub fn givens(a: T, b: T) -> (T, T) {
+        if b == T::zero() {
+            (T::one(), T::zero())
+        } else {
+            let r = a.hypot(b);
+            (a / r, b / r)
+        }
+    }
+
+    /// Rotate rows `i` and `j` in place:
+    /// `row_i = c row_i + s row_j`, `row_j = -s row_i + c row_j`
+    pub fn apply_givens_rows(&mut self, i: usize, j: usize, c: T, s: T) {
+        for k in 0..self.get_cols() {
+            let x = self.get(i, k).unwrap();
+            let y = self.get(j, k).unwrap();
+            self.set(i, k, c * x + s * y);
+            self.set(j, k, c * y - s * x);
+        }
+    }
+}
+
+/// Householder vector `v = x + sign(x_0) |x| e_1`, chosen to avoid
+/// cancellation
+fn householder_vector<T: Float>(x: &[T]) -> Vec<T> {
+    let mut v = x.to_vec();
+    if let Some(v0) = v.first_mut() {
+        let norm = dot(x, x).sqrt();
+        let sign = if *v0 < T::zero() { -T::one() } else { T::one() };
+        *v0 = *v0 + sign * norm;
+    }
+    v
+}
+
+
+/// Apply the reflector `I - 2 v v^T / (v^T v)` from the left to rows
+/// `r0..r0 + v.len()`, limited to the given columns
+fn reflect_rows<T: Float>(a: &mut [Vec<T>], v: &[T], r0: usize, cols: Range<usize>) {
+    let vv = dot(v, v);
+    if vv == T::zero() {
+        return;
+    }
+    let two = T::one() + T::one();
+    for c in cols {
+        let s = v.iter().enumerate()
+            .fold(T::zero(), |acc, (i, &vi)| acc + vi * a[r0 + i][c]);
+        let f = two * s / vv;
+        for (i, &vi) in v.iter().enumerate() {
+            a[r0 + i][c] = a[r0 + i][c] - f * vi;
+        }
+    }
+}
+
+/// Apply the reflector `I - 2 v v^T / (v^T v)` from the right to columns
+/// `c0..c0 + v.len()`, limited to the given rows
+fn reflect_cols<T: Float>(a: &mut [Vec<T>], v: &[T], c0: usize, rows: Range<usize>) {
+    let vv = dot(v, v);
+    if vv == T::zero() {
+        return;
+    }
+    let two = T::one() + T::one();
+    for r in rows {
+        let row = &mut a[r][c0..c0 + v.len()];
+        let f = two * dot(row, v) / vv;
+        for (x, &vi) in row.iter_mut().zip(v) {
+            *x = *x - f * vi;
+        }
+    }
+}
+
+/// Reduce the 2x2 diagonal block at `m` of a Schur form to upper
+/// triangular when its eigenvalues are real, updating `q` to match
+fn split_2x2<T: Float>(h: &mut [Vec<T>], q: &mut [Vec<T>], m: usize) {
+    let (a, b) = (h[m][m], h[m][m + 1]);
+    let (c, d) = (h[m + 1][m], h[m + 1][m + 1]);
+    let two = T::one() + T::one();
+    let p = (a - d) / two;
+    let disc = p * p + b * c;
+    if disc < T::zero() {
+        return;
+    }
+
+    // Rotate the eigenvector (lambda - d, c) onto e_1
+    let r = if p >= T::zero() { disc.sqrt() } else { -disc.sqrt() };
+    let (v1, v2) = (p + r, c);
+    let nv = v1.hypot(v2);
+    if nv == T::zero() {
+        return;
+    }
+    let (cs, sn) = (v1 / nv, v2 / nv);
+    let (top, bottom) = h.split_at_mut(m + 1);
+    for (x, y) in top[m][m..].iter_mut().zip(bottom[0][m..].iter_mut()) {
+        let (u, w) = (*x, *y);
+        *x = cs * u + sn * w;
+        *y = cs * w - sn * u;
+    }
+    for row in h.iter_mut().take(m + 2).chain(q.iter_mut()) {
+        let (x, y) = (row[m], row[m + 1]);
+        row[m] = cs * x + sn * y;
+        row[m + 1] = cs * y - sn * x;
+    }
+    h[m + 1][m] = T::zero();
+}
+
+//=============================================================================
+//Eigenvalues
+//=============================================================================
+impl<'a, T: Float> Matrix<'a, T> {
+
+    /// Dominant eigenvalue and its unit eigenvector by power iteration.
+    /// Converged once the residual `|A v - lambda v|` is at most `tol`
+    /// times `|A v|`; returns `None` if that does not happen within
+    /// `max_iter` iterations, as when the dominant eigenvalues are `+-lambda`
+    /// or a complex pair.
+    pub fn power_iteration(&self, max_iter: usize, tol: T) -> Option<(T, Vector<T>)> {
+        assert!(self.get_rows() == self.get_cols(), "Matrix is not square");
+        let n = self.get_rows();
+        let mut v = vec![T::one() / T::from(n).unwrap().sqrt(); n];
+
+        for _ in 0..max_iter {
+            let w = self.mul_slice(&v);
+            let norm = dot(&w, &w).sqrt();
+            if norm == T::zero() {
+                // v lies in the null space
+                return Some((T::zero(), Vector::new(v)));
+            }
+            let lambda = dot(&v, &w);
+            let res = w.iter().zip(v.iter())
+                .fold(T::zero(), |acc, (&wi, &vi)| acc + (wi - lambda * vi).powi(2))
+                .sqrt();
+            if res <= tol * norm {
+                return Some((lambda, Vector::new(v)));
+            }
+            v = w.into_iter().map(|x| x / norm).collect();
+        }
+        None
+    }
+
+    /// Spectral radius: the largest eigenvalue magnitude, read off the
+    /// real Schur form so that `+-lambda` and complex pairs are handled.
+    /// Panics if the Schur iteration does not converge.
+    pub fn spectral_radius(&self) -> T {
+        let (_, t) = self.schur();
+        let n = t.get_rows();
+        let mut radius = T::zero();
+        let mut i = 0;
+        while i < n {
+            if i + 1 < n && t.get(i + 1, i).unwrap() != T::zero() {
+                // 2x2 block: eigenvalues are the roots of x^2 - tr x + det
+                let (a, b) = (t.get(i, i).unwrap(), t.get(i, i + 1).unwrap());
+                let (c, d) = (t.get(i + 1, i).unwrap(), t.get(i + 1, i + 1).unwrap());
+                let half = (a + d) / (T::one() + T::one());
+                let det = a * d - b * c;
+                let disc = half * half - det;
+                let m = if disc >= T::zero() {
+                    half.abs() + disc.sqrt()
+                } else {
+                    det.abs().sqrt()
+                };
+                radius = radius.max(m);
+                i += 2;
+            } else {
+                radius = radius.max(t.get(i, i).unwrap().abs());
+                i += 1;
+            }
+        }
+        radius
+    }
+
+    /// Product with a vector given as a slice
+    fn mul_slice(&self, x: &[T]) -> Vec<T> {
+        assert!(x.len() == self.get_cols(), "Dimension mismatch");
+        (0..self.get_rows())
+            .map(|i| (0..self.get_cols())
+                .fold(T::zero(), |acc, j| acc + self.get(i, j).unwrap() * x[j]))
+            .collect()
+    }
+
+    /// Coefficients of the characteristic polynomial `det(x I - A)` by the
+    /// Faddeev-LeVerrier algorithm, lowest power first: element `k`
+    /// multiplies `x^k`, and the leading coefficient is one
+    pub fn characteristic_poly(&self) -> Vector<T> {
+        assert!(self.get_rows() == self.get_cols(), "Matrix is not square");
+        let n = self.get_rows();
+        let mut c = vec![T::zero(); n + 1];
+        c[n] = T::one();
+
+        let mut m = Matrix::zero(n, n);
+        for k in 1..=n {
+            m.add_to_diagonal(c[n + 1 - k]);
+            m = self.matmul(&m);
+            c[n - k] = -m.trace() / T::from(k).unwrap();
+        }
+        Vector::new(c)
+    }
+
+    /// Eigenvalues in ascending order and the matching orthonormal
+    /// eigenvectors (as columns) of a symmetric matrix, by cyclic Jacobi
+    /// rotations
+    pub fn eig_symmetric(&self) -> (Vector<T>, Matrix<'a, T>) {
+        assert!(self.get_rows() == self.get_cols(), "Matrix is not square");
+        let n = self.get_rows();
+        let mut a = Matrix::from_fn(n, n, |i, j| self.get(i, j).unwrap());
+        let mut vt = Matrix::eye(n);
+        let tol = T::epsilon() * self.frobenius_norm();
+        let two = T::one() + T::one();
+
+        for _ in 0..100 {
+            let off = (0..n).flat_map(|i| (i + 1..n).map(move |j| (i, j)))
+                .fold(T::zero(), |acc, (i, j)| acc + a.get(i, j).unwrap().powi(2));
+            if off.sqrt() <= tol {
+                break;
+            }
+            for p in 0..n {
+                for q in p + 1..n {
+                    let apq = a.get(p, q).unwrap();
+                    if apq == T::zero() {
+                        continue;
+                    }
+                    let theta = (a.get(q, q).unwrap() - a.get(p, p).unwrap()) / (two * apq);
+                    let t = theta.signum() / (theta.abs() + theta.hypot(T::one()));
+                    let c = T::one() / t.hypot(T::one());
+                    let s = t * c;
+
+                    a.apply_givens_rows(p, q, c, -s);
+                    for k in 0..n {
+                        let x = a.get(k, p).unwrap();
+                        let y = a.get(k, q).unwrap();
+                        a.set(k, p, c * x - s * y);
+                        a.set(k, q, s * x + c * y);
+                    }
+                    vt.apply_givens_rows(p, q, c, -s);
+                }
+            }
+        }
+
+        let order = a.diagonal().argsort();
+        let vals = Vector::from_fn(|k| a.get(order[k], order[k]).unwrap(), n);
+        (vals, Matrix::from_fn(n, n, |i, k| vt.get(order[k], i).unwrap()))
+    }
+
+    /// Generalized eigenproblem `A v = lambda B v` for symmetric `A` and
+    /// symmetric positive definite `B`. Returns the eigenvalues in
+    /// ascending order and the eigenvectors as columns, normalized so that
+    /// `V^T B V = I`.
+    pub fn generalized_eig_symmetric(&self, b: &Matrix<'a, T>) -> (Vector<T>, Matrix<'a, T>) {
+        assert!(self.get_shape() == b.get_shape(), "Shapes do not match");
+        let l = b.cholesky().expect("Matrix is not positive definite");
+        let linv = l.inverse().unwrap();
+        let c = linv.matmul(self).matmul(&linv.transpose()).symmetrize();
+        let (vals, w) = c.eig_symmetric();
+        (vals, linv.transpose().matmul(&w))
+    }
+}
+
+/// One for true, zero for false
+fn indicator<T: Float>(b: bool) -> T {
+    if b { T::one() } else { T::zero() }
+}
+
+/// Arithmetic mean of a slice
+fn mean<T: Float>(x: &[T]) -> T {
+    x.iter().fold(T::zero(), |acc, &v| acc + v) / T::from(x.len()).unwrap()
+}
+
+/// Dot product of two slices
+fn dot<T: Float>(a: &[T], b: &[T]) -> T {
+    a.iter().zip(b).fold(T::zero(), |acc, (&x, &y)| acc + x * y)
+}
+
+
+//=============================================================================
+//Matrix functions
+//=============================================================================
+impl<'a, T: Float> Matrix<'a, T> {
+
+    /// `A^p` for symmetric positive definite `A`, by raising the eigenvalues
+    /// to the power `p`. Panics if `A` is not symmetric positive definite.
+    pub fn fractional_pow(&self, p: T) -> Matrix<'a, T> {
+        self.map_spectrum(|l| l.powf(p))
+            .expect("Matrix is not symmetric positive definite")
+    }
+
+    /// Principal matrix square root of a symmetric positive definite
+    /// matrix, or `None` for any other matrix. Unlike `sqrt` this is not
+    /// elementwise: the result `S` satisfies `S S = A`.
+    pub fn sqrtm(&self) -> Option<Matrix<'a, T>> {
+        self.map_spectrum(|l| l.sqrt())
+    }
+
+    /// Principal matrix logarithm of a symmetric positive definite matrix,
+    /// or `None` for any other matrix
+    pub fn logm(&self) -> Option<Matrix<'a, T>> {
+        self.map_spectrum(|l| l.ln())
+    }
+
+    /// Matrix exponential of a square matrix by scaling and squaring with
+    /// a degree 6 Pade approximant. Every element of the result is NaN if
+    /// any element of the input is NaN or infinite.
+    pub fn expm(&self) -> Matrix<'a, T> {
+        assert!(self.get_rows() == self.get_cols(), "Matrix is not square");
+        if !self.is_finite() {
+            return self.map_elems(|_| T::nan());
+        }
+        let n = self.get_rows();
+        let half = T::from(0.5).unwrap();
+        // Scale the Frobenius norm down to at most 1/2. Its log is taken
+        // relative to the largest element so that it cannot overflow, which
+        // bounds the squarings by the exponent range of T.
+        let big = self.data.iter().fold(T::zero(), |m, &x| m.max(x.abs()));
+        let squarings = if big == T::zero() {
+            0
+        } else {
+            let ss = self.data.iter().fold(T::zero(), |acc, &x| acc + (x / big) * (x / big));
+            let log_norm = big.log2() + ss.log2() * half;
+            (log_norm + T::one()).ceil().max(T::zero()).to_i32().unwrap()
+        };
+        let a = self.map_elems(|x| x * half.powi(squarings));
+
+        let q = 6;
+        let mut c = T::one();
+        let mut power = Matrix::eye(n);
+        let mut num = Matrix::eye(n);
+        let mut den = Matrix::eye(n);
+        for k in 1..=q {
+            c = c * T::from(q - k + 1).unwrap() / T::from(k * (2 * q - k + 1)).unwrap();
+            power = power.matmul(&a);
+            let sign = if k % 2 == 0 { c } else { -c };
+            num = num.zip_elems(&power, |x, y| x + c * y);
+            den = den.zip_elems(&power, |x, y| x + sign * y);
+        }
+
+        let mut e = den.inverse().expect("Pade denominator is singular").matmul(&num);
+        for _ in 0..squarings {
+            e = e.matmul(&e);
+        }
+        e
+    }
+
+    /// `V f(L) V^T` from the eigendecomposition `A = V L V^T`, or `None`
+    /// unless `A` is symmetric with positive eigenvalues
+    fn map_spectrum<F>(&self, f: F) -> Option<Matrix<'a, T>>
+        where F: Fn(T) -> T {
+            assert!(self.get_rows() == self.get_cols(), "Matrix is not square");
+            let n = self.get_rows();
+            let scale = self.get_data().iter().fold(T::zero(), |m, &x| m.max(x.abs()));
+            if !self.approx_eq(&self.transpose(), scale * T::epsilon() * T::from(n).unwrap()) {
+                return None;
+            }
+            let (vals, v) = self.symmetrize().eig_symmetric();
+            if vals.iter().any(|&l| l.is_nan() || l <= T::zero()) {
+                return None;
+            }
+            let fv = v.apply_indexed(|_, j, x| x * f(vals.get_data()[j]));
+            Some(fv.matmul(&v.transpose()))
+        }
+}
+
+
+//=============================================================================
+//Iterative solvers
+//=============================================================================
+impl<'a, T: Float> Matrix<'a, T> {
+
+    /// Solve `A x = b` for symmetric positive definite `A` by conjugate
+    /// gradients, starting from zero. Returns `None` if the residual norm
+    /// is still above `tol` after `max_iter` iterations.
+    pub fn cg(&self, b: &Vector<T>, max_iter: usize, tol: T) -> Option<Vector<T>> {
+        assert!(self.get_rows() == self.get_cols(), "Matrix is not square");
+        assert!(b.get_size() == self.get_rows(), "Dimension mismatch");
+        let mut x = vec![T::zero(); b.get_size()];
+        let mut r = b.into_vec();
+        let mut p = r.clone();
+        let mut rr = dot(&r, &r);
+
+        for _ in 0..max_iter {
+            if rr.sqrt() <= tol {
+                break;
+            }
+            let ap = self.mul_slice(&p);
+            let alpha = rr / dot(&p, &ap);
+            for k in 0..x.len() {
+                x[k] = x[k] + alpha * p[k];
+                r[k] = r[k] - alpha * ap[k];
+            }
+            let next = dot(&r, &r);
+            let beta = next / rr;
+            rr = next;
+            for (pk, &rk) in p.iter_mut().zip(r.iter()) {
+                *pk = rk + beta * *pk;
+            }
+        }
+        if rr.sqrt() <= tol { Some(Vector::new(x)) } else { None }
+    }
+
+    /// Solve `A x = b` by Jacobi iteration from zero, which converges for
+    /// strictly diagonally dominant `A`. Returns `None` if the residual
+    /// norm is still above `tol` after `max_iter` sweeps or the iterates
+    /// blow up.
+    pub fn jacobi(&self, b: &Vector<T>, max_iter: usize, tol: T) -> Option<Vector<T>> {
+        self.relax(b, max_iter, tol, |x| self.jacobi_sweep(b, x)).map(|(x, _)| x)
+    }
+
+    /// Solve `A x = b` by Gauss-Seidel iteration from zero. Each sweep
+    /// uses the components already updated in that sweep, which usually
+    /// converges faster than `jacobi`. Returns `None` under the same
+    /// conditions.
+    pub fn gauss_seidel(&self, b: &Vector<T>, max_iter: usize, tol: T) -> Option<Vector<T>> {
+        self.relax(b, max_iter, tol, |x| self.gauss_seidel_sweep(b, x)).map(|(x, _)| x)
+    }
+
+    /// One Jacobi sweep: every component is updated from the previous
+    /// iterate
+    fn jacobi_sweep(&self, b: &Vector<T>, x: &mut [T]) {
+        let old = x.to_vec();
+        for (i, xi) in x.iter_mut().enumerate() {
+            let row = self.lane(&Axis::Row, i);
+            let off = dot(&row, &old) - row[i] * old[i];
+            *xi = (b.as_slice()[i] - off) / row[i];
+        }
+    }
+
+    /// One Gauss-Seidel sweep, updating the components in place in order
+    fn gauss_seidel_sweep(&self, b: &Vector<T>, x: &mut [T]) {
+        for i in 0..x.len() {
+            let row = self.lane(&Axis::Row, i);
+            let off = dot(&row, x) - row[i] * x[i];
+            x[i] = (b.as_slice()[i] - off) / row[i];
+        }
+    }
+
+    /// Run `sweep` on the iterate, starting from zero, until the residual
+    /// norm of `A x = b` is at most `tol`. Returns the solution and the
+    /// number of sweeps, or `None` on divergence or after `max_iter` sweeps.
+    fn relax<F>(&self, b: &Vector<T>, max_iter: usize, tol: T, mut sweep: F)
+        -> Option<(Vector<T>, usize)>
+        where F: FnMut(&mut [T]) {
+            assert!(self.get_rows() == self.get_cols(), "Matrix is not square");
+            assert!(b.get_size() == self.get_rows(), "Dimension mismatch");
+            let mut x = vec![T::zero(); b.get_size()];
+            let residual = |x: &[T]| {
+                let r: Vec<T> = self.mul_slice(x).iter().zip(b.iter())
+                    .map(|(&p, &q)| p - q).collect();
+                dot(&r, &r).sqrt()
+            };
+
+            for it in 0..=max_iter {
+                let res = residual(&x);
+                if res <= tol {
+                    return Some((Vector::new(x), it));
+                }
+                if !res.is_finite() || it == max_iter {
+                    break;
+                }
+                sweep(&mut x);
+            }
+            None
+        }
+}
+
+
+//=============================================================================
+//Input and output
+//=============================================================================
+impl<'a, T: Float> Matrix<'a, T> {
+
+    /// Read a real MatrixMarket file in coordinate or array format.
+    /// Symmetric and skew-symmetric storage is expanded.
+    pub fn from_matrix_market<R: Read>(r: &mut R) -> Result<Matrix<'a, T>, MatrixError> {
+        let mut text = String::new();
+        r.read_to_string(&mut text)?;
+        let mut lines = text.lines();
+
+        let header = lines.next()
+            .ok_or_else(|| parse_err("empty input"))?
+            .to_lowercase();
+        let banner: Vec<&str> = header.split_whitespace().collect();
+        if banner.len() != 5 || banner[0] != "%%matrixmarket" || banner[1] != "matrix" {
+            return Err(parse_err("invalid MatrixMarket header"));
+        }
+        let coordinate = match banner[2] {
+            "coordinate" => true,
+            "array" => false,
+            f => return Err(parse_err(&format!("unknown format '{}'", f))),
+        };
+        let pattern = match banner[3] {
+            "real" | "double" | "integer" => false,
+            "pattern" if coordinate => true,
+            f => return Err(parse_err(&format!("unsupported field '{}'", f))),
+        };
+        let sign = match banner[4] {
+            "general" => None,
+            "symmetric" => Some(T::one()),
+            "skew-symmetric" => Some(-T::one()),
+            s => return Err(parse_err(&format!("unsupported symmetry '{}'", s))),
+        };
+
+        let mut body = lines
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty() && !l.starts_with('%'));
+        let size: Vec<usize> = body.next()
+            .ok_or_else(|| parse_err("missing size line"))?
+            .split_whitespace()
+            .map(|t| t.parse().map_err(|_| parse_err("invalid size line")))
+            .collect::<Result<_, _>>()?;
+        if size.len() != if coordinate { 3 } else { 2 } {
+            return Err(parse_err("invalid size line"));
+        }
+        let (rows, cols) = (size[0], size[1]);
+        // The mirrored write below relies on a square shape
+        if sign.is_some() && rows != cols {
+            return Err(parse_err("symmetric matrix is not square"));
+        }
+        // The size line is untrusted, so allocate fallibly
+        let len = rows.checked_mul(cols)
+            .filter(|n| n.checked_mul(std::mem::size_of::<T>()).is_some())
+            .ok_or_else(|| parse_err("matrix size overflows"))?;
+        let mut dat = Vec::new();
+        dat.try_reserve_exact(len)
+            .map_err(|_| parse_err(&format!("cannot allocate a {}x{} matrix", rows, cols)))?;
+        dat.resize(len, T::zero());
+        let mut mat = Matrix::from_vec(dat, rows, cols);
+
+        let mut put = |i: usize, j: usize, v: T| -> Result<(), MatrixError> {
+            if i >= rows || j >= cols {
+                return Err(parse_err("entry out of bounds"));
+            }
+            mat.set(i, j, v);
+            if let Some(s) = sign {
+                if i != j {
+                    mat.set(j, i, s * v);
+                }
+            }
+            Ok(())
+        };
+
+        if coordinate {
+            let mut count = 0;
+            for line in body {
+                let t: Vec<&str> = line.split_whitespace().collect();
+                if t.len() != if pattern { 2 } else { 3 } {
+                    return Err(parse_err(&format!("invalid entry '{}'", line)));
+                }
+                let i: usize = parse_token(t[0])?;
+                let j: usize = parse_token(t[1])?;
+                let v = if pattern { T::one() } else { parse_value(t[2])? };
+                if i == 0 || j == 0 {
+                    return Err(parse_err("indices are 1-based"));
+                }
+                put(i - 1, j - 1, v)?;
+                count += 1;
+            }
+            if count != size[2] {
+                return Err(parse_err("entry count does not match size line"));
+            }
+        } else {
+            let mut vals = body.flat_map(|l| l.split_whitespace());
+            for j in 0..cols {
+                let start = match sign {
+                    None => 0,
+                    Some(s) if s > T::zero() => j,
+                    Some(_) => j + 1,
+                };
+                for i in start..rows {
+                    let t = vals.next().ok_or_else(|| parse_err("missing values"))?;
+                    put(i, j, parse_value(t)?)?;
+                }
+            }
+            if vals.next().is_some() {
+                return Err(parse_err("too many values"));
+            }
+        }
+        Ok(mat)
+    }
+
+    /// Write the matrix as a MatrixMarket array file
+    pub fn to_matrix_market<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        writeln!(w, "%%MatrixMarket matrix array real general")?;
+        writeln!(w, "{} {}", self.get_rows(), self.get_cols())?;
+        for j in 0..self.get_cols() {
+            for i in 0..self.get_rows() {
+                writeln!(w, "{}", self.get(i, j).unwrap().to_f64().unwrap())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Read a version 1.0 NumPy `.npy` file holding a 1-D or 2-D `f4` or
+    /// `f8` array. Fortran-ordered data is kept in column-major storage;
+    /// 1-D arrays become a single row.
+    pub fn from_npy<R: Read>(r: &mut R) -> Result<Matrix<'a, T>, MatrixError> {
+        let mut pre = [0u8; 10];
+        r.read_exact(&mut pre)?;
+        if &pre[..6] != b"\x93NUMPY" || pre[6] != 1 {
+            return Err(parse_err("not a version 1 .npy file"));
+        }
+        let hlen = u16::from_le_bytes([pre[8], pre[9]]) as usize;
+        let mut hbuf = vec![0u8; hlen];
+        r.read_exact(&mut hbuf)?;
+        let header = String::from_utf8(hbuf)
+            .map_err(|_| parse_err("header is not ASCII"))?;
+
+        let descr = npy_field(&header, "descr")?
+            .trim_start_matches(['\'', '"'])
+            .split(['\'', '"'])
+            .next().unwrap_or("");
+        let fortran = match npy_field(&header, "fortran_order")? {
+            f if f.starts_with("True") => true,
+            f if f.starts_with("False") => false,
+            _ => return Err(parse_err("invalid fortran_order")),
+        };
+        let shape = npy_field(&header, "shape")?;
+        let shape: Vec<usize> = shape[..shape.find(')').unwrap_or(0)]
+            .trim_start_matches('(')
+            .split(',')
+            .map(|t| t.trim())
+            .filter(|t| !t.is_empty())
+            .map(parse_token)
+            .collect::<Result<_, _>>()?;
+        let (rows, cols) = match shape.len() {
+            1 => (1, shape[0]),
+            2 => (shape[0], shape[1]),
+            _ => return Err(parse_err("only 1-D and 2-D arrays are supported")),
+        };
+
+        let (size, little) = match descr {
+            "<f8" => (8, true),
+            ">f8" => (8, false),
+            "<f4" => (4, true),
+            ">f4" => (4, false),
+            d => return Err(parse_err(&format!("unsupported dtype '{}'", d))),
+        };
+        let len = rows.checked_mul(cols).and_then(|n| n.checked_mul(size))
+            .ok_or_else(|| parse_err("array size overflows"))?;
+        let raw = read_bytes(r, len)?;
+        let data: Vec<T> = raw.chunks(size).map(|b| {
+            let v = match (size, little) {
+                (8, true) => f64::from_le_bytes(b.try_into().unwrap()),
+                (8, false) => f64::from_be_bytes(b.try_into().unwrap()),
+                (_, true) => f64::from(f32::from_le_bytes(b.try_into().unwrap())),
+                (_, false) => f64::from(f32::from_be_bytes(b.try_into().unwrap())),
+            };
+            T::from(v).unwrap()
+        }).collect();
+
+        if fortran {
+            Ok(Matrix {
+                data: Vector { data },
+                rows,
+                cols,
+                strd: rows,
+                mode: Axis::Column,
+                mark: PhantomData::<&'a T>,
+            })
+        } else {
+            Ok(Matrix::from_vec(data, rows, cols))
+        }
+    }
+
+    /// Write a version 1.0 NumPy `.npy` file with little-endian data.
+    /// Column-major storage is written as-is with `fortran_order` set.
+    pub fn to_npy<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let single = std::mem::size_of::<T>() == 4;
+        let mut header = format!(
+            "{{'descr': '<f{}', 'fortran_order': {}, 'shape': ({}, {}), }}",
+            if single { 4 } else { 8 },
+            if self.mode == Axis::Column { "True" } else { "False" },
+            self.get_rows(), self.get_cols());
+        while (10 + header.len() + 1) % 64 != 0 {
+            header.push(' ');
+        }
+        header.push('\n');
+
+        w.write_all(b"\x93NUMPY\x01\x00")?;
+        w.write_all(&(header.len() as u16).to_le_bytes())?;
+        w.write_all(header.as_bytes())?;
+        for &v in self.data.iter() {
+            let v = v.to_f64().unwrap();
+            if single {
+                w.write_all(&(v as f32).to_le_bytes())?;
+            } else {
+                w.write_all(&v.to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Save the matrix to `path` in a compact binary layout: a magic tag,
+    /// the element size, the storage axis, rows and cols as little-endian
+    /// u64, then the raw little-endian data
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut w = BufWriter::new(File::create(path)?);
+        let size = std::mem::size_of::<T>();
+        w.write_all(b"RMAT")?;
+        w.write_all(&[size as u8, if self.mode == Axis::Row { 0 } else { 1 }])?;
+        w.write_all(&(self.get_rows() as u64).to_le_bytes())?;
+        w.write_all(&(self.get_cols() as u64).to_le_bytes())?;
+        for &v in self.data.iter() {
+            let v = v.to_f64().unwrap();
+            if size == 4 {
+                w.write_all(&(v as f32).to_le_bytes())?;
+            } else {
+                w.write_all(&v.to_le_bytes())?;
+            }
+        }
+        w.flush()
+    }
+
+    /// Load a matrix written by `save`
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Matrix<'a, T>> {
+        let invalid = |s: &str| io::Error::new(io::ErrorKind::InvalidData, s.to_string());
+        let mut r = BufReader::new(File::open(path)?);
+        let mut head = [0u8; 22];
+        r.read_exact(&mut head)?;
+        if &head[..4] != b"RMAT" {
+            return Err(invalid("not a saved matrix"));
+        }
+        let size = head[4] as usize;
+        if size != 4 && size != 8 {
+            return Err(invalid("unsupported element size"));
+        }
+        let mode = match head[5] {
+            0 => Axis::Row,
+            1 => Axis::Column,
+            _ => return Err(invalid("invalid storage axis")),
+        };
+        let dim = |b: &[u8]| usize::try_from(u64::from_le_bytes(b.try_into().unwrap()))
+            .map_err(|_| invalid("matrix size overflows"));
+        let rows = dim(&head[6..14])?;
+        let cols = dim(&head[14..22])?;
+
+        let len = rows.checked_mul(cols).and_then(|n| n.checked_mul(size))
+            .ok_or_else(|| invalid("matrix size overflows"))?;
+        let raw = read_bytes(&mut r, len)?;
+        let data = raw.chunks(size).map(|b| {
+            let v = if size == 4 {
+                f64::from(f32::from_le_bytes(b.try_into().unwrap()))
+            } else {
+                f64::from_le_bytes(b.try_into().unwrap())
+            };
+            T::from(v).unwrap()
+        }).collect();
+
+        let strd = if mode == Axis::Row { cols } else { rows };
+        Ok(Matrix {
+            data: Vector { data },
+            rows,
+            cols,
+            strd,
+            mode,
+            mark: PhantomData::<&'a T>,
+        })
+    }
+}
+
+fn parse_err(s: &str) -> MatrixError {
+    MatrixError::Parse(String::from(s))
+}
+
+fn parse_token<U: std::str::FromStr>(t: &str) -> Result<U, MatrixError> {
+    t.parse().map_err(|_| parse_err(&format!("invalid token '{}'", t)))
+}
+
+/// Text following `'key':` in a `.npy` header dictionary
+fn npy_field<'h>(header: &'h str, key: &str) -> Result<&'h str, MatrixError> {
+    let pat = format!("'{}':", key);
+    header.find(&pat)
+        .map(|i| header[i + pat.len()..].trim_start())
+        .ok_or_else(|| parse_err(&format!("missing '{}' in header", key)))
+}
+
+fn parse_value<T: Float>(t: &str) -> Result<T, MatrixError> {
+    let v: f64 = parse_token(t)?;
+    T::from(v).ok_or_else(|| parse_err(&format!("value '{}' out of range", t)))
+}
+
+/// Read exactly `len` bytes, growing the buffer as data arrives so that a
+/// corrupt length fails at end of input rather than in one huge allocation
+fn read_bytes<R: Read>(r: &mut R, len: usize) -> io::Result<Vec<u8>> {
+    let mut raw = Vec::new();
+    r.take(len as u64).read_to_end(&mut raw)?;
+    if raw.len() != len {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "data ends early"));
+    }
+    Ok(raw)
+}
+
+
+//=============================================================================
+//Transpose view
+//=============================================================================
+/// Read-only transpose of a borrowed matrix, without copying
+#[derive(Debug, Clone, Copy)]
+pub struct TransposeView<'a, T: Float> {
+    inner: &'a Matrix<'a, T>,
+}
+
+impl<'a, T: Float> TransposeView<'a, T> {
+
+    pub fn get_rows(&self) -> usize {
+        self.inner.get_cols()
+    }
+
+    pub fn get_cols(&self) -> usize {
+        self.inner.get_rows()
+    }
+
+    pub fn get_shape(&self) -> (usize, usize) {
+        (self.get_rows(), self.get_cols())
+    }
+
+    /// Get the value from the specified location
+    pub fn get(&self, rid: usize, cid: usize) -> Option<T> {
+        self.inner.get(cid, rid)
+    }
+}
+
+impl<'a, T: Float> Matrix<'a, T> {
+
+    /// Transpose view of the matrix that reads through to its data
+    pub fn t_view(&self) -> TransposeView<'_, T> {
+        TransposeView { inner: self }
+    }
+}
+
+//=============================================================================
+//Symmetric matrix
+//=============================================================================
+/// Symmetric matrix in packed storage: only the lower triangle is kept,
+/// row by row, in `n (n + 1) / 2` elements
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymMatrix<T: Float> {
+    data: Vector<T>,
+    n: usize,
+}
+
+impl<T: Float> SymMatrix<T> {
+
+    /// Pack the lower triangle of a square matrix; the upper triangle is
+    /// not read
+    pub fn from_dense(m: &Matrix<T>) -> SymMatrix<T> {
+        assert!(m.get_rows() == m.get_cols(), "Matrix is not square");
+        let n = m.get_rows();
+        let mut dat = Vec::with_capacity(n * (n + 1) / 2);
+        for i in 0..n {
+            for j in 0..=i {
+                dat.push(m.get(i, j).unwrap());
+            }
+        }
+        SymMatrix { data: Vector::new(dat), n }
+    }
+
+    /// Dense copy with both triangles filled in
+    pub fn to_dense<'a>(&self) -> Matrix<'a, T> {
+        Matrix::from_fn(self.n, self.n, |i, j| self.uget(i, j))
+    }
+
+    pub fn get_rows(&self) -> usize {
+        self.n
+    }
+
+    pub fn get_cols(&self) -> usize {
+        self.n
+    }
+
+    pub fn get_shape(&self) -> (usize, usize) {
+        (self.n, self.n)
+    }
+
+    /// Get the value from the specified location
+    pub fn get(&self, rid: usize, cid: usize) -> Option<T> {
+        if rid >= self.n || cid >= self.n {
+            None
+        } else {
+            Some(self.uget(rid, cid))
+        }
+    }
+
+    /// Product with a vector. Each stored element is read once and used
+    /// for both of its mirrored positions.
+    pub fn matvec(&self, x: &Vector<T>) -> Vector<T> {
+        assert!(x.get_size() == self.n, "Dimension mismatch");
+        let x = x.as_slice();
+        let mut y = vec![T::zero(); self.n];
+        let mut k = 0;
+        for i in 0..self.n {
+            for j in 0..i {
+                let a = self.data.as_slice()[k];
+                y[i] = y[i] + a * x[j];
+                y[j] = y[j] + a * x[i];
+                k += 1;
+            }
+            y[i] = y[i] + self.data.as_slice()[k] * x[i];
+            k += 1;
+        }
+        Vector::new(y)
+    }
+
+    /// Value at a position assumed to be in bounds
+    fn uget(&self, rid: usize, cid: usize) -> T {
+        let (i, j) = if rid >= cid { (rid, cid) } else { (cid, rid) };
+        self.data.as_slice()[i * (i + 1) / 2 + j]
+    }
+}
+
+//=============================================================================
+//Sparse matrix
+//=============================================================================
+/// Sparse matrix in compressed sparse row (CSR) form: the nonzeros of row
+/// `i` are `values[indptr[i]..indptr[i + 1]]`, at columns given by the
+/// matching `indices`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SparseMatrix<T: Float> {
+    indptr: Vec<usize>,
+    indices: Vec<usize>,
+    values: Vec<T>,
+    rows: usize,
+    cols: usize,
+}
+
+impl<T: Float> SparseMatrix<T> {
+
+    /// Store the nonzero elements of a dense matrix
+    pub fn from_dense(m: &Matrix<T>) -> SparseMatrix<T> {
+        let mut indptr = vec![0];
+        let mut indices = Vec::new();
+        let mut values = Vec::new();
+        for i in 0..m.get_rows() {
+            for j in 0..m.get_cols() {
+                let v = m.get(i, j).unwrap();
+                if v != T::zero() {
+                    indices.push(j);
+                    values.push(v);
+                }
+            }
+            indptr.push(values.len());
+        }
+        SparseMatrix { indptr, indices, values, rows: m.get_rows(), cols: m.get_cols() }
+    }
+
+    /// Dense copy
+    pub fn to_dense<'a>(&self) -> Matrix<'a, T> {
+        let mut m = Matrix::zero(self.rows, self.cols);
+        for i in 0..self.rows {
+            for k in self.indptr[i]..self.indptr[i + 1] {
+                m.set(i, self.indices[k], self.values[k]);
+            }
+        }
+        m
+    }
+
+    pub fn get_rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn get_cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn get_shape(&self) -> (usize, usize) {
+        (self.rows, self.cols)
+    }
+
+    /// Number of stored elements
+    pub fn nnz(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Get the value from the specified location
+    pub fn get(&self, rid: usize, cid: usize) -> Option<T> {
+        if rid >= self.rows || cid >= self.cols {
+            return None;
+        }
+        let lo = self.indptr[rid];
+        let hi = self.indptr[rid + 1];
+        match self.indices[lo..hi].binary_search(&cid) {
+            Ok(k) => Some(self.values[lo + k]),
+            Err(_) => Some(T::zero()),
+        }
+    }
+
+    /// Product with a vector, touching only the stored elements
+    pub fn matvec(&self, x: &Vector<T>) -> Vector<T> {
+        assert!(x.get_size() == self.cols, "Dimension mismatch");
+        let x = x.as_slice();
+        Vector::from_fn(|i| {
+            (self.indptr[i]..self.indptr[i + 1])
+                .fold(T::zero(), |acc, k| acc + self.values[k] * x[self.indices[k]])
+        }, self.rows)
+    }
+}
+
+//=============================================================================
+//Memory mapped matrix
+//=============================================================================
+/// Row major matrix backed by a memory mapped file, for data larger than
+/// memory. The file holds the raw elements and nothing else.
+#[cfg(feature = "mmap")]
+#[derive(Debug)]
+pub struct MmapMatrix<T: MmapElem> {
+    map: memmap2::MmapMut,
+    rows: usize,
+    cols: usize,
+    mark: PhantomData<T>,
+}
+
+/// Element types of an `MmapMatrix`. The file bytes are read in place as
+/// elements, so this is only implemented for `f32` and `f64`, for which
+/// every bit pattern is a valid value.
+#[cfg(feature = "mmap")]
+pub trait MmapElem: Float + sealed::Sealed {}
+
+#[cfg(feature = "mmap")]
+impl MmapElem for f32 {}
+
+#[cfg(feature = "mmap")]
+impl MmapElem for f64 {}
+
+#[cfg(feature = "mmap")]
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for f32 {}
+    impl Sealed for f64 {}
+}
+
+#[cfg(feature = "mmap")]
+impl<T: MmapElem> MmapMatrix<T> {
+
+    /// Map the file at `path` as a `rows` x `cols` matrix. A missing or
+    /// empty file is created and zero filled; an existing file must have
+    /// exactly the matching size.
+    pub fn open<P: AsRef<Path>>(path: P, rows: usize, cols: usize)
+        -> io::Result<MmapMatrix<T>> {
+            let len = rows.checked_mul(cols)
+                .and_then(|n| n.checked_mul(std::mem::size_of::<T>()))
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput,
+                                              "matrix size overflows"))? as u64;
+            let file = std::fs::OpenOptions::new()
+                .read(true).write(true).create(true).truncate(false)
+                .open(path)?;
+            match file.metadata()?.len() {
+                0 => file.set_len(len)?,
+                l if l == len => {},
+                _ => return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                               "file size does not match shape")),
+            }
+            let map = unsafe { memmap2::MmapMut::map_mut(&file)? };
+            Ok(MmapMatrix { map, rows, cols, mark: PhantomData })
+        }
+
+    pub fn get_rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn get_cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn get_shape(&self) -> (usize, usize) {
+        (self.rows, self.cols)
+    }
+
+    /// Get the value from the specified location
+    pub fn get(&self, rid: usize, cid: usize) -> Option<T> {
+        if rid >= self.rows || cid >= self.cols {
+            None
+        } else {
+            Some(self.as_slice()[rid * self.cols + cid])
+        }
+    }
+
+    /// Set the value at the specified location
+    pub fn set(&mut self, rid: usize, cid: usize, val: T) {
+        assert!(rid < self.rows && cid < self.cols, "Index out of bounds");
+        let cols = self.cols;
+        self.as_mut_slice()[rid * cols + cid] = val;
+    }
+
+    /// Write pending changes back to the file
+    pub fn flush(&self) -> io::Result<()> {
+        self.map.flush()
+    }
+
+    fn as_slice(&self) -> &[T] {
+        // The map is page aligned and sized for rows * cols elements
+        unsafe {
+            std::slice::from_raw_parts(self.map.as_ptr() as *const T,
+                                       self.rows * self.cols)
+        }
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe {
+            std::slice::from_raw_parts_mut(self.map.as_mut_ptr() as *mut T,
+                                           self.rows * self.cols)
+        }
+    }
+}
+
+    ///Print the matrix
+    impl<'a, T: Float + Display + Debug> fmt::Display for Matrix<'a, T>
+    {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write_aligned(f, self, 0, |v| format!("{:1.5}", v))
+        }
+    }
+
+    /// Print the matrix in scientific notation
+    pub struct SciDisplay<'m, 'a, T: Float>(pub &'m Matrix<'a, T>);
+
+    impl<'m, 'a, T: Float + LowerExp> fmt::Display for SciDisplay<'m, 'a, T>
+    {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write_aligned(f, self.0, 0, |v| format!("{:e}", v))
+        }
+    }
+
+    /// Print the matrix with a chosen precision and minimum field width
+    #[derive(Debug, Clone, Copy)]
+    pub struct DisplayOpts<'m, T: Float> {
+        mat: &'m Matrix<'m, T>,
+        precision: usize,
+        width: usize,
+    }
+
+    impl<'m, T: Float> DisplayOpts<'m, T> {
+        /// Display options with the default precision of 5 digits
+        pub fn new(mat: &'m Matrix<'m, T>) -> DisplayOpts<'m, T> {
+            DisplayOpts { mat, precision: 5, width: 0 }
+        }
+
+        /// Digits after the decimal point
+        pub fn precision(mut self, precision: usize) -> DisplayOpts<'m, T> {
+            self.precision = precision;
+            self
+        }
+
+        /// Minimum field width of each element
+        pub fn width(mut self, width: usize) -> DisplayOpts<'m, T> {
+            self.width = width;
+            self
+        }
+    }
+
+    impl<'m, T: Float + Display> fmt::Display for DisplayOpts<'m, T>
+    {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            let p = self.precision;
+            write_aligned(f, self.mat, self.width, |v| format!("{:.p$}", v, p = p))
+        }
+    }
+
+    /// Write the elements right-aligned in columns of at least `min_width`,
+    /// one row per line. NaN and signed infinities are spelled out, finite
+    /// values go through `fmt_finite`.
+    fn write_aligned<T, F>(f: &mut fmt::Formatter, m: &Matrix<T>, min_width: usize,
+                           fmt_finite: F) -> fmt::Result
+        where T: Float, F: Fn(T) -> String {
+            let cells: Vec<String> = (0..m.get_rows())
+                .flat_map(|i| (0..m.get_cols()).map(move |j| (i, j)))
+                .map(|(i, j)| match m.get(i, j) {
+                    Some(v) if v.is_nan() => String::from("NaN"),
+                    Some(v) if v.is_infinite() => {
+                        String::from(if v > T::zero() { "+inf" } else { "-inf" })
+                    },
+                    Some(v) => fmt_finite(v),
+                    None => String::from("?"),
+                })
+                .collect();
+            let width = cells.iter().map(|c| c.len()).fold(min_width, usize::max);
+
+            for row in cells.chunks(m.get_cols().max(1)) {
+                for c in row {
+                    write!(f, "{:>w$} ", c, w = width)?;
+                }
+                writeln!(f)?;
+            }
+            Ok(())
+        }
+
+
+
+
+
+//=============================================================================
+//Matrix Slice
+//=============================================================================
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct MatrixSlice<'a, T> {
+    pt: *const T,
+    nr: usize,
+    nc: usize,
+    rs: usize,
+    _m: PhantomData<&'a T>,
+}
+
+//=============================================================================
+//Mutable Matrix Slice
+//=============================================================================
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct MatrixMutSlice<'a, T> {
+    pt: *mut T,
+    nr: usize,
+    nc: usize,
+    rs: usize,
+    _m: PhantomData<&'a T>,
+}
+
+//=============================================================================
+//Immutable Row slice from matrix 
+//=============================================================================
+#[derive(Debug, Clone, Copy)]
+pub struct Row<'a, T> {
+    row: MatrixSlice<'a, T>,
+}
+
+//=============================================================================
+//Mutable Row Slice from matrix
+//=============================================================================
+#[derive(Debug, Clone, Copy)]
+pub struct RowMut<'a, T> {
+    row: MatrixMutSlice<'a, T>,
+}
+
+//=============================================================================
+//Immutable Row Iter
+//=============================================================================
+#[derive(Debug, Clone, Copy)]
+pub struct RowsIter<'a, T> {
+    start_pos: *const T,
+    row_pos: usize,
+    row_slice: usize,
+    col_slice: usize,
+    row_stride: usize,
+    _markr: PhantomData<&'a T>,
+}
+
+//=============================================================================
+//Mutable Row Iter
+//=============================================================================
+#[derive(Debug, Clone, Copy)]
+pub struct RowsMutIter<'a, T> {
+    start_pos: *mut T,
+    row_pos: usize,
+    row_slice: usize,
+    col_slice: usize,
+    row_stride: usize,
+    _markr: PhantomData<&'a T>,
+}
+
+//=============================================================================
+//Immutable Column slice from matrix
+//=============================================================================
+#[derive(Debug, Clone, Copy)]
+pub struct Col<'a, T> {
+    col: MatrixSlice<'a, T>,
+}
+
+//=============================================================================
+//Mutable column slice from matrix
+//=============================================================================
+#[derive(Debug, Clone, Copy)]
+pub struct ColMut<'a, T> {
+    col: MatrixMutSlice<'a, T>,
+}
+
+//=============================================================================
+//Immutable column iter
+//=============================================================================
+#[derive(Debug, Clone, Copy)]
+pub struct ColIter<'a, T> {
+    start_pos: *const T,
+    col_pos: usize,
+    row_slice: usize,
+    col_slice: usize,
+    col_stride: usize,
+    _markr: PhantomData<&'a T>,
+}
+
+//=============================================================================
+//Mutable column iter
+//=============================================================================
+#[derive(Debug, Clone, Copy)]
+pub struct ColMutIter<'a, T> {
+    start_pos: *mut T,
+    col_pos: usize,
+    row_slice: usize,
+    col_slice: usize,
+    col_stride: usize,
+    _markr: PhantomData<&'a T>,
+}
+
+//=============================================================================
+//Iterate over slice data immutably
+//=============================================================================
+#[derive(Debug, Clone, Copy)]
+pub struct SliceIter<'a, T> {
+    slice: *const T,
+    row_pos: usize,
+    col_pos: usize,
+    row_slice: usize,
+    col_slice: usize,
+    _markr: PhantomData<&'a T>,
+}
+
+//=============================================================================
+//Iterate over slice data mutably
+//=============================================================================
+#[derive(Debug, Clone, Copy)]
+pub struct SliceMutIter<'a, T> {
+    slice: *mut T,
+    row_pos: usize,
+    col_pos: usize,
+    row_slice: usize,
+    col_slice: usize,
+    _markr: PhantomData<&'a T>,
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Column major matrix over `dat`, for exercising that storage mode
+    fn col_major<'a>(dat: Vec<f64>, rows: usize, cols: usize) -> Matrix<'a, f64> {
+        assert!(rows * cols == dat.len());
+        Matrix {
+            data: Vector::new(dat),
+            rows,
+            cols,
+            strd: rows,
+            mode: Axis::Column,
+            mark: PhantomData,
+        }
+    }
+
+    #[test]
+    fn get_ref_from_shared_matrix() {
+        let m = Matrix::from_vec(vec![1.0, 2.0, 3.0, 4.0], 2, 2);
+        let r = &m;
+
+        assert_eq!(r.get_ref([1, 0]), Some(&3.0));
+        assert_eq!(m.get_elem_ref([0, 1]), Some(&2.0));
+        assert_eq!(m.get_ref([2, 0]), None);
+    }
+
+    #[test]
+    fn get_tall_matrix() {
+        let m = Matrix::from_vec(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], 3, 2);
+
+        assert_eq!(m.get(2, 1), Some(6.0));
+        assert_eq!(m.get(0, 2), None);
+        assert_eq!(m.transpose().get(1, 2), Some(6.0));
+    }
+
+    #[test]
+    fn transpose_materializes_and_eq_is_logical() {
+        let s = Matrix::from_vec(vec![1.0, 2.0, 2.0, 5.0], 2, 2);
+        assert_eq!(s, s.transpose());
+
+        let m = Matrix::from_vec(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], 2, 3);
+        let t = m.transpose();
+        assert_eq!(t.get_mode(), Axis::Row);
+        assert_eq!(t.get_data(), &vec![1.0, 4.0, 2.0, 5.0, 3.0, 6.0]);
+        assert_eq!(t, col_major(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], 3, 2));
+        assert!(m != Matrix::from_vec(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], 3, 2));
+        assert_eq!(m.get(2, 0), None);
+    }
+
+    #[test]
+    fn display_nan_and_inf() {
+        let m = Matrix::from_vec(vec![1.0, f64::NAN, f64::INFINITY,
+                                      f64::NEG_INFINITY], 2, 2);
+        let out = format!("{}", m);
+        let lines: Vec<&str> = out.lines().collect();
+
+        assert_eq!(lines, vec!["1.00000     NaN ", "   +inf    -inf "]);
+    }
+
+    #[test]
+    fn approx_eq_rel_large_magnitudes() {
+        let a = Matrix::from_vec(vec![1.0e12, 0.0, -3.0e9, 2.0], 2, 2);
+        let b = Matrix::from_vec(vec![1.0e12 + 1.0e3, 0.0, -3.0e9 - 10.0, 2.0],
+                                 2, 2);
+
+        assert!(!a.approx_eq(&b, 1e-6));
+        assert!(a.approx_eq_rel(&b, 1e-6));
+        assert!(!a.approx_eq_rel(&b, 1e-12));
+        assert!(!a.approx_eq_rel(&Matrix::zero(2, 1), 1e-6));
+    }
+
+    #[test]
+    fn softmax_axis_sums_to_one() {
+        let m = Matrix::from_vec(vec![1.0, 2.0, 3.0,
+                                      1000.0, 1001.0, 1002.0], 2, 3);
+
+        let r = m.softmax_axis(Axis::Row);
+        for i in 0..2 {
+            let s: f64 = (0..3).map(|j| r.get(i, j).unwrap()).sum();
+            assert!((s - 1.0).abs() < 1e-12);
+            assert!((r.get(i, 2).unwrap() - 0.66524095).abs() < 1e-8);
+        }
+
+        let c = m.softmax_axis(Axis::Column);
+        for j in 0..3 {
+            let s: f64 = (0..2).map(|i| c.get(i, j).unwrap()).sum();
+            assert!((s - 1.0).abs() < 1e-12);
+            assert!(c.get(1, j).unwrap().is_finite());
+        }
+    }
+
+    #[test]
+    fn argmax_rows_picks_class() {
+        let m = Matrix::from_vec(vec![0.1, 0.7, 0.2,
+                                      0.5, 0.3, 0.2,
+                                      0.0, 0.1, 0.9], 3, 3);
+
+        assert_eq!(m.argmax_rows(), vec![1, 0, 2]);
+    }
+
+    #[test]
+    fn argmax_rows_nan() {
+        let m = Matrix::from_vec(vec![f64::NAN, 2.0, 1.0,
+                                      f64::NAN, f64::NAN, f64::NAN,
+                                      3.0, 3.0, f64::NAN], 3, 3);
+
+        assert_eq!(m.argmax_rows(), vec![1, 0, 0]);
+        assert!(Matrix::<f64>::zero(0, 3).argmax_rows().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Matrix has no columns")]
+    fn argmax_rows_no_columns() {
+        Matrix::<f64>::zero(3, 0).argmax_rows();
+    }
+
+    #[test]
+    fn anti_diagonal_3x3() {
+        let m = Matrix::from_vec(vec![1.0, 2.0, 3.0,
+                                      4.0, 5.0, 6.0,
+                                      7.0, 8.0, 9.0], 3, 3);
+
+        assert_eq!(m.anti_diagonal().into_vec(), vec![3.0, 5.0, 7.0]);
+    }
+
+    #[test]
+    fn step_view_4x4() {
+        let m = Matrix::from_fn(4, 4, |i, j| (i * 4 + j) as f64);
+        let s = m.step_view(2, 2);
+
+        assert_eq!(s.get_shape(), (2, 2));
+        assert_eq!(s.get_data(), &vec![0.0, 2.0, 8.0, 10.0]);
+        assert_eq!(m.step_view(3, 1).get_shape(), (2, 4));
+    }
+
+    #[test]
+    fn diagonal_k_offsets() {
+        let m = Matrix::from_vec(vec![1.0, 2.0, 3.0,
+                                      4.0, 5.0, 6.0,
+                                      7.0, 8.0, 9.0], 3, 3);
+
+        assert_eq!(m.diagonal().into_vec(), vec![1.0, 5.0, 9.0]);
+        assert_eq!(m.diagonal_k(1).into_vec(), vec![2.0, 6.0]);
+        assert_eq!(m.diagonal_k(-1).into_vec(), vec![4.0, 8.0]);
+        assert_eq!(m.diagonal_k(-2).into_vec(), vec![7.0]);
+        assert!(m.diagonal_k(3).is_empty());
+    }
+
+    #[test]
+    fn tensordot_axes() {
+        let a = Matrix::from_vec(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], 3, 2);
+        let b = Matrix::from_vec(vec![1.0, 0.0, 2.0, -1.0, 3.0, 1.0], 3, 2);
+
+        let c = a.tensordot(&b, Axis::Row, Axis::Row);
+        assert_eq!(c.get_shape(), (2, 2));
+        assert!(c.approx_eq(&a.transpose().matmul(&b), 1e-12));
+        assert_eq!(c.get_data(), &vec![22.0, 2.0, 28.0, 2.0]);
+
+        let d = a.tensordot(&b, Axis::Column, Axis::Column);
+        assert!(d.approx_eq(&a.matmul(&b.transpose()), 1e-12));
+    }
+
+    #[test]
+    fn det_sign_row_swap() {
+        let i: Matrix<f64> = Matrix::eye(3);
+        let p = Matrix::from_vec(vec![0.0, 1.0, 0.0,
+                                      1.0, 0.0, 0.0,
+                                      0.0, 0.0, 1.0], 3, 3);
+        let s = Matrix::from_vec(vec![1.0, 2.0, 2.0, 4.0], 2, 2);
+
+        assert_eq!(i.det_sign(), 1);
+        assert_eq!(p.det_sign(), -1);
+        assert_eq!(p.matmul(&Matrix::diag(&vec![-1.0, 2.0, 3.0], 3, 3))
+                   .det_sign(), 1);
+        assert_eq!(s.det_sign(), 0);
+    }
+
+    #[test]
+    fn spectral_radius_dominant() {
+        let a = Matrix::from_vec(vec![2.0, 1.0, 1.0, 2.0], 2, 2);
+        let b = Matrix::from_vec(vec![1.0, 2.0, 3.0, -4.0], 2, 2);
+
+        assert!((a.spectral_radius() - 3.0).abs() < 1e-8);
+        assert!((b.spectral_radius() - 5.0).abs() < 1e-8);
+        assert_eq!(Matrix::from_vec(vec![0.0, 1.0, 0.0, 0.0], 2, 2).spectral_radius(), 0.0);
+    }
+
+    #[test]
+    fn power_iteration_convergence() {
+        let flip = Matrix::diag(&vec![1.0, -1.0], 2, 2);
+        let rot = Matrix::from_vec(vec![0.0, -1.0, 1.0, 0.0], 2, 2);
+
+        assert!(flip.power_iteration(1000, 1e-10).is_none());
+        assert!(rot.power_iteration(1000, 1e-10).is_none());
+        let (l, v) = Matrix::from_vec(vec![2.0, 1.0, 1.0, 2.0], 2, 2)
+            .power_iteration(1000, 1e-12).unwrap();
+        assert!((l - 3.0).abs() < 1e-10);
+        assert!((v.get_data()[0] - v.get_data()[1]).abs() < 1e-10);
+    }
+
+    #[test]
+    fn spectral_radius_hard_cases() {
+        let flip = Matrix::diag(&vec![1.0, -1.0], 2, 2);
+        let rot = Matrix::from_vec(vec![0.0, -1.0, 1.0, 0.0], 2, 2);
+        let spiral = Matrix::from_vec(vec![3.0, -4.0, 4.0, 3.0], 2, 2);
+
+        assert!((flip.spectral_radius() - 1.0).abs() < 1e-12);
+        assert!((Matrix::diag(&vec![3.0, -3.0, 1.0], 3, 3).spectral_radius() - 3.0).abs() < 1e-12);
+        assert!((rot.spectral_radius() - 1.0).abs() < 1e-12);
+        assert!((spiral.spectral_radius() - 5.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn pow_signed_negative() {
+        let a = Matrix::from_vec(vec![4.0, 7.0, 2.0, 6.0], 2, 2);
+        let inv = a.inverse().unwrap();
+
+        assert!(a.matmul(&inv).approx_eq(&Matrix::eye(2), 1e-12));
+        assert!(a.pow_signed(-1).unwrap().approx_eq(&inv, 1e-12));
+        assert!(a.pow_signed(-2).unwrap().approx_eq(&inv.matmul(&inv), 1e-12));
+        assert!(a.pow_signed(3).unwrap()
+                .approx_eq(&a.matmul(&a).matmul(&a), 1e-9));
+        assert!(a.pow_signed(0).unwrap().approx_eq(&Matrix::eye(2), 0.0));
+        assert!(Matrix::from_vec(vec![1.0, 2.0, 2.0, 4.0], 2, 2)
+                .pow_signed(-1).is_none());
+    }
+
+    #[test]
+    fn hadamard_div_ieee() {
+        let a = Matrix::from_vec(vec![6.0, -4.0, 1.0, 0.0], 2, 2);
+        let b = Matrix::from_vec(vec![3.0, 8.0, 0.0, 0.0], 2, 2);
+        let q = a.hadamard_div(&b);
+
+        assert_eq!(q.get(0, 0), Some(2.0));
+        assert_eq!(q.get(0, 1), Some(-0.5));
+        assert_eq!(q.get(1, 0), Some(f64::INFINITY));
+        assert!(q.get(1, 1).unwrap().is_nan());
+    }
+
+    #[test]
+    fn clamp_bounds() {
+        let mut m = Matrix::from_vec(vec![-2.0, 0.5, 3.0, 1.0], 2, 2);
+        let c = m.clamp(0.0, 1.0);
+
+        assert_eq!(c.get_data(), &vec![0.0, 0.5, 1.0, 1.0]);
+        m.clamp_mut(-1.0, 2.0);
+        assert_eq!(m.get_data(), &vec![-1.0, 0.5, 2.0, 1.0]);
+
+        let mut n = Matrix::from_vec(vec![f64::NAN, 5.0], 1, 2);
+        assert!(n.clamp(0.0, 1.0).get(0, 0).unwrap().is_nan());
+        n.clamp_mut(0.0, 1.0);
+        assert!(n.get(0, 0).unwrap().is_nan());
+        assert_eq!(n.get(0, 1), Some(1.0));
+    }
+
+    #[test]
+    fn abs_signum_rounding() {
+        let m = Matrix::from_vec(vec![-1.5, 2.25, -0.4, 3.7], 2, 2);
+
+        assert_eq!(m.abs().get_data(), &vec![1.5, 2.25, 0.4, 3.7]);
+        assert_eq!(m.signum().get_data(), &vec![-1.0, 1.0, -1.0, 1.0]);
+        assert_eq!(m.round().get_data(), &vec![-2.0, 2.0, -0.0, 4.0]);
+        assert_eq!(m.floor().get_data(), &vec![-2.0, 2.0, -1.0, 3.0]);
+        assert_eq!(m.ceil().get_data(), &vec![-1.0, 3.0, -0.0, 4.0]);
+    }
+
+    #[test]
+    fn sum_and_product() {
+        let m = Matrix::from_vec(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], 2, 3);
+
+        assert_eq!(m.sum(), 21.0);
+        assert_eq!(m.product(), 720.0);
+        assert_eq!(m.transpose().sum(), 21.0);
+    }
+
+    #[test]
+    fn cumsum_axis_2x3() {
+        let m = Matrix::from_vec(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], 2, 3);
+
+        assert_eq!(m.cumsum_axis(Axis::Row).get_data(),
+                   &vec![1.0, 3.0, 6.0, 4.0, 9.0, 15.0]);
+        assert_eq!(m.cumsum_axis(Axis::Column).get_data(),
+                   &vec![1.0, 2.0, 3.0, 5.0, 7.0, 9.0]);
+    }
+
+    #[test]
+    fn diff_axis_inverts_cumsum() {
+        let m = Matrix::from_vec(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], 2, 3);
+
+        let d = m.cumsum_axis(Axis::Row).diff_axis(Axis::Row);
+        assert_eq!(d.get_shape(), (2, 2));
+        assert_eq!(d.get_data(), &vec![2.0, 3.0, 5.0, 6.0]);
+
+        let d = m.cumsum_axis(Axis::Column).diff_axis(Axis::Column);
+        assert_eq!(d.get_shape(), (1, 3));
+        assert_eq!(d.get_data(), &vec![4.0, 5.0, 6.0]);
+    }
+
+    #[test]
+    fn sort_by_column_stable() {
+        let m = Matrix::from_vec(vec![3.0, 0.0,
+                                      1.0, 1.0,
+                                      3.0, 2.0,
+                                      2.0, 3.0], 4, 2);
+
+        assert_eq!(m.sort_by_column(0, true).get_data(),
+                   &vec![1.0, 1.0, 2.0, 3.0, 3.0, 0.0, 3.0, 2.0]);
+        assert_eq!(m.sort_by_column(0, false).get_data(),
+                   &vec![3.0, 0.0, 3.0, 2.0, 2.0, 3.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn sort_by_column_nan_key() {
+        let m = Matrix::from_fn(40, 2, |i, j| match j {
+            0 if i % 3 == 0 => f64::NAN,
+            0 => ((i * 7) % 11) as f64,
+            _ => i as f64,
+        });
+
+        for &asc in [true, false].iter() {
+            let key = m.sort_by_column(0, asc).lane(&Axis::Column, 0);
+            let (vals, nans) = key.split_at(40 - 14);
+            assert!(nans.iter().all(|x| x.is_nan()));
+            assert!(vals.windows(2).all(|w| if asc { w[0] <= w[1] } else { w[0] >= w[1] }));
+        }
+    }
+
+    #[test]
+    fn find_above_threshold() {
+        let m = Matrix::from_vec(vec![1.0, 5.0, 3.0, 7.0, 0.0, 6.0], 2, 3);
+
+        assert_eq!(m.find(|x| x > 4.0), vec![(0, 1), (1, 0), (1, 2)]);
+        assert_eq!(m.transpose().find(|x| x > 4.0), vec![(0, 1), (1, 0), (2, 1)]);
+        assert!(m.find(|x| x > 10.0).is_empty());
+    }
+
+    #[test]
+    fn select_mask_identity() {
+        let m = Matrix::from_vec(vec![1.0, 2.0, 3.0,
+                                      4.0, 5.0, 6.0,
+                                      7.0, 8.0, 9.0], 3, 3);
+
+        assert_eq!(m.select_mask(&Matrix::eye(3)).into_vec(), vec![1.0, 5.0, 9.0]);
+    }
+
+    #[test]
+    fn block_diag_two_blocks() {
+        let a = Matrix::from_vec(vec![1.0, 2.0, 3.0, 4.0], 2, 2);
+        let b = Matrix::from_vec(vec![5.0, 6.0, 7.0, 8.0], 2, 2);
+        let m = Matrix::block_diag(&[a, b]);
+
+        assert_eq!(m.get_shape(), (4, 4));
+        assert_eq!(m.get_data(), &vec![1.0, 2.0, 0.0, 0.0,
+                                       3.0, 4.0, 0.0, 0.0,
+                                       0.0, 0.0, 5.0, 6.0,
+                                       0.0, 0.0, 7.0, 8.0]);
+    }
+
+    #[test]
+    fn matrix_market_dense() {
+        let text = "%%MatrixMarket matrix array real general\n\
+                    % a comment\n\
+                    2 3\n\
+                    1\n4\n2\n5\n3\n6\n";
+        let m: Matrix<f64> = Matrix::from_matrix_market(&mut text.as_bytes()).unwrap();
+
+        assert_eq!(m.get_shape(), (2, 3));
+        assert_eq!(m.get_data(), &vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+    }
+
+    #[test]
+    fn matrix_market_coordinate_and_errors() {
+        let text = "%%MatrixMarket matrix coordinate real symmetric\n\
+                    2 2 2\n1 1 1.5\n2 1 -2\n";
+        let m: Matrix<f64> = Matrix::from_matrix_market(&mut text.as_bytes()).unwrap();
+        assert_eq!(m.get_data(), &vec![1.5, -2.0, -2.0, 0.0]);
+
+        let bad = ["", "%%MatrixMarket matrix array complex general\n1 1\n1 0\n",
+                   "%%MatrixMarket matrix array real general\n2 2\n1\n2\n3\n",
+                   "%%MatrixMarket matrix coordinate real general\n2 2 1\n3 1 1\n",
+                   "%%MatrixMarket matrix coordinate real symmetric\n2 3 1\n1 3 1\n",
+                   "%%MatrixMarket matrix array real skew-symmetric\n3 2\n1\n2\n3\n",
+                   "%%MatrixMarket matrix coordinate real symmetric\n2 2 1\n1 3 1\n"];
+        for text in bad.iter() {
+            let r: Result<Matrix<f64>, _> = Matrix::from_matrix_market(&mut text.as_bytes());
+            assert!(r.is_err());
+        }
+    }
+
+    #[test]
+    fn matrix_market_oversized_header() {
+        let overflow = "%%MatrixMarket matrix coordinate real general\n\
+                        4000000000 4000000000 0\n";
+        let r: Result<Matrix<f64>, _> = Matrix::from_matrix_market(&mut overflow.as_bytes());
+        assert!(matches!(r, Err(MatrixError::Parse(_))));
+
+        let huge = "%%MatrixMarket matrix coordinate real general\n\
+                    1000000000 1000000000 0\n";
+        let r: Result<Matrix<f64>, _> = Matrix::from_matrix_market(&mut huge.as_bytes());
+        assert!(matches!(r, Err(MatrixError::Parse(_))));
+    }
+
+    #[test]
+    fn matrix_market_round_trip() {
+        let m = Matrix::from_vec(vec![1.0, -2.5, 1.0e-20, 3.0, 0.1, 7.0e30], 3, 2);
+        let mut buf = Vec::new();
+        m.to_matrix_market(&mut buf).unwrap();
+        let r: Matrix<f64> = Matrix::from_matrix_market(&mut buf.as_slice()).unwrap();
+
+        assert_eq!(r, m);
+    }
+
+    fn npy_bytes(header: &str, data: &[f64]) -> Vec<u8> {
+        let mut h = String::from(header);
+        while (10 + h.len() + 1) % 64 != 0 {
+            h.push(' ');
+        }
+        h.push('\n');
+        let mut out = b"\x93NUMPY\x01\x00".to_vec();
+        out.extend_from_slice(&(h.len() as u16).to_le_bytes());
+        out.extend_from_slice(h.as_bytes());
+        for v in data {
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+        out
+    }
+
+    #[test]
+    fn npy_read_2x3() {
+        let data = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+        let c = npy_bytes("{'descr': '<f8', 'fortran_order': False, 'shape': (2, 3), }",
+                          &data);
+        let m: Matrix<f64> = Matrix::from_npy(&mut c.as_slice()).unwrap();
+        assert_eq!(m.get_shape(), (2, 3));
+        assert_eq!(m.get_mode(), Axis::Row);
+        assert_eq!(m.get(1, 0), Some(4.0));
+
+        let f = npy_bytes("{'descr': '<f8', 'fortran_order': True, 'shape': (2, 3), }",
+                          &data);
+        let m: Matrix<f64> = Matrix::from_npy(&mut f.as_slice()).unwrap();
+        assert_eq!(m.get_mode(), Axis::Column);
+        assert_eq!(m.get(1, 0), Some(2.0));
+        assert_eq!(m.get(0, 2), Some(5.0));
+
+        let bad = npy_bytes("{'descr': '<i8', 'fortran_order': False, 'shape': (2, 3), }",
+                            &data);
+        assert!(Matrix::<f64>::from_npy(&mut bad.as_slice()).is_err());
+    }
+
+    #[test]
+    fn npy_huge_shape_is_an_error() {
+        let data = [1.0, 2.0];
+        let huge = npy_bytes("{'descr': '<f8', 'fortran_order': False, \
+                              'shape': (4294967296, 4294967296), }", &data);
+        match Matrix::<f64>::from_npy(&mut huge.as_slice()) {
+            Err(MatrixError::Parse(_)) => (),
+            r => panic!("expected a parse error, got {:?}", r),
+        }
+
+        let short = npy_bytes("{'descr': '<f8', 'fortran_order': False, 'shape': (1000000, 1000), }",
+                              &data);
+        match Matrix::<f64>::from_npy(&mut short.as_slice()) {
+            Err(MatrixError::Io(e)) => assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof),
+            r => panic!("expected an I/O error, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn npy_write_header_and_round_trip() {
+        let m = Matrix::from_vec(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], 2, 3);
+        let mut buf = Vec::new();
+        m.to_npy(&mut buf).unwrap();
+
+        let dict = "{'descr': '<f8', 'fortran_order': False, 'shape': (2, 3), }";
+        let header = format!("{}{}\n", dict, " ".repeat(58));
+        assert_eq!(&buf[..10], b"\x93NUMPY\x01\x00\x76\x00");
+        assert_eq!(&buf[10..128], header.as_bytes());
+        assert_eq!(buf.len(), 128 + 6 * 8);
+        assert_eq!(Matrix::from_npy(&mut buf.as_slice()).unwrap(), m);
+
+        let t = col_major(vec![1.5, -2.0], 2, 1);
+        let mut buf = Vec::new();
+        t.to_npy(&mut buf).unwrap();
+        let r: Matrix<f64> = Matrix::from_npy(&mut buf.as_slice()).unwrap();
+        assert_eq!(r.get_shape(), (2, 1));
+        assert_eq!(r.get(1, 0), Some(-2.0));
+        assert_eq!(r, t);
+
+        let s: Matrix<f32> = Matrix::from_vec(vec![1.5, -2.0], 1, 2);
+        let mut buf = Vec::new();
+        s.to_npy(&mut buf).unwrap();
+        assert_eq!(buf.len(), 128 + 2 * 4);
+        assert_eq!(Matrix::<f32>::from_npy(&mut buf.as_slice()).unwrap(), s);
+    }
+
+    #[test]
+    fn save_and_load() {
+        let path = std::env::temp_dir()
+            .join(format!("rusmat_save_{}.bin", std::process::id()));
+        let m = col_major(vec![1.0, -2.5, 3.25, 4.0, 5.0, 6.0], 2, 3);
+
+        m.save(&path).unwrap();
+        let r: Matrix<f64> = Matrix::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(r, m);
+        assert_eq!(r.get(1, 2), Some(6.0));
+    }
+
+    #[test]
+    fn load_rejects_huge_shape() {
+        let path = std::env::temp_dir()
+            .join(format!("rusmat_huge_{}.bin", std::process::id()));
+        let mut bytes = b"RMAT\x08\x00".to_vec();
+        bytes.extend_from_slice(&(1u64 << 32).to_le_bytes());
+        bytes.extend_from_slice(&(1u64 << 32).to_le_bytes());
+        std::fs::write(&path, &bytes).unwrap();
+
+        let r = Matrix::<f64>::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(r.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn sci_display_exponents() {
+        let m = Matrix::from_vec(vec![1.5e-12, 2.0e20, -3.0, f64::NAN], 2, 2);
+        let out = format!("{}", SciDisplay(&m));
+        let lines: Vec<&str> = out.lines().collect();
+
+        assert_eq!(lines, vec!["1.5e-12    2e20 ", "   -3e0     NaN "]);
+    }
+
+    #[test]
+    fn display_opts_precision() {
+        let m = Matrix::from_vec(vec![1.0 / 3.0, 2.0 / 3.0], 1, 2);
+
+        assert_eq!(format!("{}", DisplayOpts::new(&m).precision(2)), "0.33 0.67 \n");
+        assert_eq!(format!("{}", DisplayOpts::new(&m).precision(8).width(12)),
+                   "  0.33333333   0.66666667 \n");
+        assert_eq!(format!("{}", m), "0.33333 0.66667 \n");
+    }
+
+    #[test]
+    fn rows_round_trip() {
+        let m = col_major(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], 3, 2);
+        let rows = m.to_rows();
+
+        assert_eq!(rows, vec![vec![1.0, 4.0], vec![2.0, 5.0], vec![3.0, 6.0]]);
+        assert!(Matrix::from_rows(rows).approx_eq(&m, 0.0));
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn mmap_matrix_persists() {
+        let path = std::env::temp_dir()
+            .join(format!("rusmat_mmap_{}.bin", std::process::id()));
+        {
+            let mut m: MmapMatrix<f64> = MmapMatrix::open(&path, 2, 3).unwrap();
+            assert_eq!(m.get_shape(), (2, 3));
+            assert_eq!(m.get(1, 2), Some(0.0));
+            m.set(1, 2, 4.5);
+            m.set(0, 1, -1.0);
+            m.flush().unwrap();
+        }
+        let m: MmapMatrix<f64> = MmapMatrix::open(&path, 2, 3).unwrap();
+        let wrong = MmapMatrix::<f64>::open(&path, 3, 3);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(m.get(1, 2), Some(4.5));
+        assert_eq!(m.get(0, 1), Some(-1.0));
+        assert_eq!(m.get(2, 0), None);
+        assert!(wrong.is_err());
+
+        let huge = MmapMatrix::<f64>::open(&path, 1 << 32, 1 << 32);
+        assert_eq!(huge.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn row_chunks_reassemble() {
+        let m = Matrix::from_fn(5, 2, |i, j| (i * 2 + j) as f64);
+        let chunks: Vec<Matrix<f64>> = m.row_chunks(2).collect();
+
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[2].get_shape(), (1, 2));
+        let rows: Vec<Vec<f64>> = chunks.iter().flat_map(|c| c.to_rows()).collect();
+        assert_eq!(Matrix::from_rows(rows), m);
+    }
+
+    #[test]
+    fn t_view_reads_transpose() {
+        let m = Matrix::from_vec(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], 2, 3);
+        let t = m.t_view();
+
+        assert_eq!(t.get_shape(), (3, 2));
+        for i in 0..3 {
+            for j in 0..2 {
+                assert_eq!(t.get(i, j), m.get(j, i));
+            }
+        }
+        assert_eq!(t.get(0, 2), None);
+    }
+
+    #[test]
+    fn apply_scalar_relu() {
+        let m = Matrix::from_vec(vec![-1.0, 2.0, -0.5, 0.0], 2, 2);
+
+        assert_eq!(m.apply_scalar(0.0, |x, s| x.max(s)).get_data(),
+                   &vec![0.0, 2.0, 0.0, 0.0]);
+        assert_eq!(m.apply_scalar(1.0, |x, s| x + s).get_data(),
+                   &vec![0.0, 3.0, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn householder_zeros_below_first() {
+        let x = Vector::new(vec![3.0, 1.0, 5.0, 1.0]);
+        let h = Matrix::householder(&x);
+        let hx = h.matmul(&Matrix::from_vec(x.into_vec(), 4, 1));
+
+        assert!((hx.get(0, 0).unwrap().abs() - 6.0).abs() < 1e-12);
+        for i in 1..4 {
+            assert!(hx.get(i, 0).unwrap().abs() < 1e-12);
+        }
+        assert!(h.matmul(&h.transpose()).approx_eq(&Matrix::eye(4), 1e-12));
+        assert_eq!(Matrix::householder(&Vector::new(vec![0.0, 0.0])),
+                   Matrix::eye(2));
+    }
+
+    #[test]
+    fn givens_zeros_target() {
+        let mut m = Matrix::from_vec(vec![1.0, 2.0, 3.0,
+                                          3.0, 1.0, 2.0,
+                                          4.0, 5.0, 6.0], 3, 3);
+        let (c, s) = Matrix::givens(3.0, 4.0);
+        m.apply_givens_rows(1, 2, c, s);
+
+        assert!((m.get(1, 0).unwrap() - 5.0).abs() < 1e-12);
+        assert!(m.get(2, 0).unwrap().abs() < 1e-12);
+        assert_eq!(m.get(0, 2), Some(3.0));
+        assert_eq!(Matrix::<f64>::givens(2.0, 0.0), (1.0, 0.0));
+    }
+
+    fn assert_schur<'a>(a: &Matrix<'a, f64>) -> Matrix<'a, f64> {
+        let n = a.get_rows();
+        let (q, t) = a.schur();
+
+        assert!(q.matmul(&q.transpose()).approx_eq(&Matrix::eye(n), 1e-10));
+        assert!(q.matmul(&t).matmul(&q.transpose()).approx_eq(a, 1e-9));
+        for i in 0..n {
+            for j in 0..i.saturating_sub(1) {
+                assert_eq!(t.get(i, j), Some(0.0));
+            }
+            if i + 1 < n {
+                let below = t.get(i + 1, i).unwrap();
+                assert!(below == 0.0 || i + 2 >= n || t.get(i + 2, i + 1) == Some(0.0));
+            }
+        }
+        t
+    }
+
+    #[test]
+    fn schur_complex_pair() {
+        let a = Matrix::from_vec(vec![4.0, -2.0, 1.0, 3.0,
+                                      3.0, 6.0, -4.0, 2.0,
+                                      1.0, 2.0, 5.0, -1.0,
+                                      2.0, -3.0, 1.0, 0.0], 4, 4);
+        let t = assert_schur(&a);
+
+        assert!((t.diagonal().into_vec().iter().sum::<f64>() - 15.0).abs() < 1e-9);
+        assert!((0..3).any(|i| t.get(i + 1, i) != Some(0.0)));
+    }
+
+    #[test]
+    fn schur_real_eigenvalues() {
+        let a = Matrix::from_vec(vec![2.0, 1.0, 0.0,
+                                      1.0, 3.0, 1.0,
+                                      0.0, 1.0, 4.0], 3, 3);
+        let t = assert_schur(&a);
+
+        for i in 0..2 {
+            assert_eq!(t.get(i + 1, i), Some(0.0));
+        }
+        let mut ev = t.diagonal().into_vec();
+        ev.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert!((ev[1] - 3.0).abs() < 1e-9);
+        assert!((ev[0] - (3.0 - 3f64.sqrt())).abs() < 1e-9);
+    }
+
+    #[test]
+    #[should_panic(expected = "Schur iteration did not converge")]
+    fn schur_nan_does_not_converge() {
+        Matrix::from_vec(vec![1.0, 2.0, 0.0,
+                              f64::NAN, 3.0, 1.0,
+                              0.0, 1.0, 4.0], 3, 3).schur();
+    }
+
+    #[test]
+    fn bidiagonalize_reconstructs() {
+        let a = Matrix::from_vec(vec![1.0, 2.0, 3.0,
+                                      4.0, 5.0, 6.0,
+                                      7.0, 8.0, 10.0,
+                                      -1.0, 0.5, 2.0], 4, 3);
+        for m in [a.clone(), a.transpose()].iter() {
+            let (u, b, v) = m.bidiagonalize();
+            let (r, c) = m.get_shape();
+
+            assert!(u.matmul(&u.transpose()).approx_eq(&Matrix::eye(r), 1e-12));
+            assert!(v.matmul(&v.transpose()).approx_eq(&Matrix::eye(c), 1e-12));
+            assert!(u.matmul(&b).matmul(&v.transpose()).approx_eq(m, 1e-12));
+            for i in 0..r {
+                for j in 0..c {
+                    if j != i && j != i + 1 {
+                        assert_eq!(b.get(i, j), Some(0.0));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn from_fn_stateful_closure() {
+        let mut count = 0.0;
+        let m = Matrix::from_fn(2, 3, |_, _| {
+            count += 1.0;
+            count
+        });
+
+        assert_eq!(m.get_data(), &vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+    }
+
+    #[test]
+    fn minmax_extremes() {
+        let m = Matrix::from_vec(vec![3.0, -7.5, 2.0, 9.25, 0.0, 1.0], 3, 2);
+
+        assert_eq!(m.minmax(), Some((-7.5, 9.25)));
+        assert_eq!(Matrix::from_vec(vec![4.0], 1, 1).minmax(), Some((4.0, 4.0)));
+        assert_eq!(Matrix::<f64>::from_vec(vec![], 0, 3).minmax(), None);
+    }
+
+    #[test]
+    fn fill_diagonal_only() {
+        let mut m = Matrix::from_fn(2, 3, |i, j| (i * 3 + j) as f64);
+        m.fill_diagonal(-1.0);
+
+        assert_eq!(m.get_data(), &vec![-1.0, 1.0, 2.0, 3.0, -1.0, 5.0]);
+    }
+
+    #[test]
+    fn add_to_diagonal_ridge() {
+        let a = Matrix::from_vec(vec![2.0, 1.0, 0.5, 1.0, 3.0, -1.0, 0.5, -1.0, 4.0], 3, 3);
+        let lambda = 0.25;
+        let mut b = a.clone();
+        b.add_to_diagonal(lambda);
+
+        let expected = a.zip_elems(&Matrix::eye(3), |x, e| x + lambda * e);
+        assert_eq!(b, expected);
+    }
+
+    #[test]
+    fn count_nonzero_tolerance() {
+        let m = Matrix::from_vec(vec![0.0, 1e-12, -3.0, 2.0, -1e-15, 0.5], 2, 3);
+
+        assert_eq!(m.count_nonzero(0.0), 5);
+        assert_eq!(m.count_nonzero(1e-9), 3);
+    }
+
+    #[test]
+    fn any_and_all() {
+        let mut m = Matrix::from_vec(vec![1.0, 2.0, 3.0, 4.0], 2, 2);
+
+        assert!(!m.any(|x| x.is_nan()));
+        assert!(m.all(|x| x >= 0.0));
+        m.set(1, 0, f64::NAN);
+        m.set(0, 1, -2.0);
+        assert!(m.any(|x| x.is_nan()));
+        assert!(!m.all(|x| x >= 0.0));
+    }
+
+    #[test]
+    fn is_finite_detects_inf() {
+        let mut m = Matrix::from_vec(vec![1.0, -2.0, 0.0, 4.0], 2, 2);
+
+        assert!(m.is_finite());
+        m.set(1, 1, f64::INFINITY);
+        assert!(!m.is_finite());
+    }
+
+    #[test]
+    fn tuple_accessors() {
+        let mut m = Matrix::from_vec(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], 2, 3);
+
+        assert_eq!(m.at((1, 2)), Some(6.0));
+        assert_eq!(m.at((2, 0)), None);
+        assert_eq!(m.at((0, 3)), None);
+        m.set_at((0, 2), -3.0);
+        assert_eq!(m.get(0, 2), Some(-3.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Index out of bounds")]
+    fn set_at_out_of_bounds() {
+        let mut m = Matrix::from_vec(vec![1.0, 2.0], 1, 2);
+        m.set_at((1, 0), 0.0);
+    }
+
+    #[test]
+    fn row_and_col_means() {
+        let m = Matrix::from_vec(vec![1.0, 2.0, 6.0, 4.0, 8.0, 0.0], 2, 3);
+
+        assert_eq!(m.row_means().into_vec(), vec![3.0, 4.0]);
+        assert_eq!(m.col_means().into_vec(), vec![2.5, 5.0, 3.0]);
+    }
+
+    #[test]
+    fn argwhere_coordinates() {
+        let m = Matrix::from_vec(vec![0.5, 3.0, -1.0, 4.0, 2.0, 8.0], 2, 3);
+        let w = m.argwhere(|x| x > 2.5);
+
+        assert_eq!(w.get_shape(), (3, 2));
+        assert_eq!(w.to_rows(), vec![vec![0, 1], vec![1, 0], vec![1, 2]]);
+        assert_eq!(m.argwhere(|x| x > 10.0).get_shape(), (0, 2));
+    }
+
+    #[test]
+    fn comparison_masks() {
+        let a = Matrix::from_vec(vec![1.0, 5.0, 3.0, -2.0], 2, 2);
+        let b = Matrix::from_vec(vec![2.0, 5.0, 1.0, 0.0], 2, 2);
+
+        assert_eq!(a.greater_than(&b).get_data(), &vec![0.0, 0.0, 1.0, 0.0]);
+        assert_eq!(a.less_than(&b).get_data(), &vec![1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(a.equal_to(&b).get_data(), &vec![0.0, 1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn clip_by_norm_scales_large() {
+        let big = Matrix::from_vec(vec![3.0, 4.0, 0.0, 12.0], 2, 2);
+        let small = Matrix::from_vec(vec![0.3, 0.4, 0.0, 0.0], 2, 2);
+
+        assert_eq!(big.frobenius_norm(), 13.0);
+        let c = big.clip_by_norm(1.0);
+        assert!((c.frobenius_norm() - 1.0).abs() < 1e-12);
+        assert!(c.approx_eq(&big.map_elems(|x| x / 13.0), 1e-12));
+        assert_eq!(small.clip_by_norm(1.0), small);
+    }
+
+    #[test]
+    fn powf_and_powi() {
+        let m = Matrix::from_vec(vec![4.0, 9.0, 2.0, 0.0], 2, 2);
+
+        assert!(m.powf(0.5).approx_eq(&m.map_elems(|x| x.sqrt()), 1e-15));
+        assert_eq!(m.powi(2).get_data(), &vec![16.0, 81.0, 4.0, 0.0]);
+        assert_eq!(m.powi(-1).get(0, 0), Some(0.25));
+    }
+
+    #[test]
+    fn exp_ln_sqrt_trig() {
+        let m = Matrix::from_vec(vec![-1.5, 0.25, 2.0, -3.0], 2, 2);
+
+        assert!(m.exp().ln().approx_eq(&m, 1e-12));
+        assert!(m.powi(2).sqrt().approx_eq(&m.abs(), 1e-12));
+        let one = m.sin().powi(2).zip_elems(&m.cos().powi(2), |a, b| a + b);
+        assert!(one.approx_eq(&Matrix::unit(2, 2), 1e-12));
+        assert!((m.tanh().get(1, 1).unwrap() - (-3f64).tanh()).abs() < 1e-15);
+    }
+
+    #[test]
+    fn normalize_trace_to_one() {
+        let rho = Matrix::from_vec(vec![3.0, 1.0, 1.0, 5.0], 2, 2);
+        let n = rho.normalize_trace();
+
+        assert_eq!(rho.trace(), 8.0);
+        assert!((n.trace() - 1.0).abs() < 1e-15);
+        assert_eq!(n.get(0, 1), Some(0.125));
+    }
+
+    #[test]
+    #[should_panic(expected = "Trace is zero")]
+    fn normalize_trace_zero() {
+        Matrix::from_vec(vec![1.0, 2.0, 3.0, -1.0], 2, 2).normalize_trace();
+    }
+
+    #[test]
+    fn symmetrize_square() {
+        let a = Matrix::from_vec(vec![1.0, 2.0, 0.0, 4.0, 5.0, 6.0, 2.0, 1.0, 9.0], 3, 3);
+        let s = a.symmetrize();
+
+        for i in 0..3 {
+            for j in 0..3 {
+                assert_eq!(s.get(i, j), s.get(j, i));
+            }
+        }
+        assert_eq!(s.get(0, 1), Some(3.0));
+        assert_eq!(s.symmetrize(), s);
+    }
+
+    #[test]
+    fn symmetric_plus_skew() {
+        let a = Matrix::from_vec(vec![1.0, 2.0, 0.0, 4.0, 5.0, 6.0, 2.0, 1.0, 9.0], 3, 3);
+        let k = a.skew();
+
+        assert!(k.approx_eq(&k.transpose().map_elems(|x| -x), 0.0));
+        assert!(a.symmetrize().zip_elems(&k, |x, y| x + y).approx_eq(&a, 1e-15));
+    }
+
+    #[test]
+    fn is_close_mask() {
+        let a = Matrix::from_vec(vec![1.0, 100.0, 1e-9, 5.0], 2, 2);
+        let b = Matrix::from_vec(vec![1.0 + 1e-10, 100.0 + 1e-4, 0.0, 5.1], 2, 2);
+
+        assert_eq!(a.is_close(&b, 1e-8, 1e-5).get_data(), &vec![1.0, 1.0, 1.0, 0.0]);
+        assert_eq!(a.is_close(&b, 0.0, 1e-5).get_data(), &vec![1.0, 1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn repeat_blocks() {
+        let m = Matrix::from_vec(vec![1.0, 2.0, 3.0, 4.0], 2, 2);
+        let r = m.repeat(2, 2);
+
+        assert_eq!(r.get_shape(), (4, 4));
+        assert_eq!(r.get_data(), &vec![1.0, 1.0, 2.0, 2.0,
+                                       1.0, 1.0, 2.0, 2.0,
+                                       3.0, 3.0, 4.0, 4.0,
+                                       3.0, 3.0, 4.0, 4.0]);
+        assert_eq!(m.repeat(1, 3).get_shape(), (2, 6));
+    }
+
+    #[test]
+    fn avg_pool_2x2() {
+        let m = Matrix::from_fn(4, 4, |i, j| (i * 4 + j) as f64);
+        let p = m.avg_pool(2, 2);
+
+        assert_eq!(p.get_shape(), (2, 2));
+        assert_eq!(p.get_data(), &vec![2.5, 4.5, 10.5, 12.5]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Pool size does not divide the matrix shape")]
+    fn avg_pool_indivisible() {
+        Matrix::<f64>::zero(3, 4).avg_pool(2, 2);
+    }
+
+    #[test]
+    fn max_pool_2x2() {
+        let m = Matrix::from_vec(vec![1.0, 5.0, -2.0, 0.0,
+                                      3.0, 2.0, -1.0, -3.0,
+                                      0.0, 0.0, 7.0, 8.0,
+                                      0.0, 9.0, 6.0, 4.0], 4, 4);
+
+        assert_eq!(m.max_pool(2, 2).get_data(), &vec![5.0, 0.0, 9.0, 8.0]);
+    }
+
+    #[test]
+    fn integral_image_box_sum() {
+        let m = Matrix::from_fn(4, 5, |i, j| ((i * 7 + j * 3) % 5) as f64 - 1.0);
+        let s = m.integral_image();
+        let at = |i: usize, j: usize| s.get(i, j).unwrap();
+
+        // Rows 1..=3, columns 2..=4
+        let direct = (1..4).flat_map(|i| (2..5).map(move |j| (i, j)))
+            .fold(0.0, |acc, (i, j)| acc + m.get(i, j).unwrap());
+        assert_eq!(at(3, 4) - at(0, 4) - at(3, 1) + at(0, 1), direct);
+        assert_eq!(at(3, 4), m.sum());
+    }
+
+    #[test]
+    fn gradient_of_ramp() {
+        let m = Matrix::from_fn(3, 5, |i, j| 2.0 * j as f64 - 3.0 * i as f64);
+
+        assert!(m.gradient(Axis::Row).approx_eq(&Matrix::from_fn(3, 5, |_, _| 2.0), 1e-12));
+        assert!(m.gradient(Axis::Column).approx_eq(&Matrix::from_fn(3, 5, |_, _| -3.0), 1e-12));
+
+        let q = Matrix::from_vec(vec![0.0, 1.0, 4.0, 9.0], 1, 4);
+        assert_eq!(q.gradient(Axis::Row).get_data(), &vec![1.0, 2.0, 4.0, 5.0]);
+    }
+
+    #[test]
+    fn close_wrapper_near_equal() {
+        let a = Matrix::from_vec(vec![1.0, 2.0, 3.0, 4.0], 2, 2);
+        let b = a.map_elems(|x| x + 1e-10);
+
+        assert_eq!(Close(&a, 1e-8), Close(&b, 1e-8));
+        assert_eq!(Close(&a, 1e-8), Close(&a.transpose().transpose(), 1e-8));
+        assert!(Close(&a, 1e-12) != Close(&b, 1e-8));
+    }
+
+    #[test]
+    #[should_panic]
+    fn close_wrapper_different() {
+        let a = Matrix::from_vec(vec![1.0, 2.0, 3.0, 4.0], 2, 2);
+        let b = Matrix::from_vec(vec![1.0, 2.0, 3.0, 4.5], 2, 2);
+
+        assert_eq!(Close(&a, 1e-8), Close(&b, 1e-8));
+    }
+
+    #[test]
+    fn as_slice_follows_storage() {
+        let mut m = Matrix::from_vec(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], 2, 3);
+        assert_eq!(m.as_slice().len(), m.get_size());
+
+        m.as_mut_slice()[4] = 50.0;
+        assert_eq!(m.get(1, 1), Some(50.0));
+
+        let mut t = col_major(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], 3, 2);
+        t.as_mut_slice()[1] = 20.0;
+        assert_eq!(t.get(1, 0), Some(20.0));
+        assert_eq!(t.as_slice(), &[1.0, 20.0, 3.0, 4.0, 5.0, 6.0]);
+    }
+
+    #[test]
+    fn try_from_row_slices() {
+        let rows: &[&[f64]] = &[&[1.0, 2.0, 3.0], &[4.0, 5.0, 6.0]];
+        let m = Matrix::try_from(rows).unwrap();
+        assert_eq!(m, Matrix::from_vec(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], 2, 3));
+
+        let ragged: &[&[f64]] = &[&[1.0, 2.0], &[3.0]];
+        match Matrix::try_from(ragged) {
+            Err(MatrixError::Shape(_)) => (),
+            _ => panic!("ragged rows accepted"),
+        }
+    }
+
+    #[test]
+    fn characteristic_poly_invariants() {
+        let a = Matrix::from_vec(vec![2.0, -1.0, 0.0, 3.0,
+                                      1.0, 4.0, 2.0, 0.0,
+                                      0.5, 0.0, -3.0, 1.0,
+                                      2.0, 1.0, 1.0, 1.0], 4, 4);
+        let p = a.characteristic_poly();
+        let c = p.get_data();
+
+        assert_eq!(c.len(), 5);
+        assert_eq!(c[4], 1.0);
+        assert!((c[3] + a.trace()).abs() < 1e-12);
+        assert!((c[0] - a.det()).abs() < 1e-9);
+
+        // det(x I - A) = (x - 2)(x - 3) for a diagonal matrix
+        let d = Matrix::from_vec(vec![2.0, 0.0, 0.0, 3.0], 2, 2);
+        assert_eq!(d.characteristic_poly().get_data(), &vec![6.0, -5.0, 1.0]);
+        assert_eq!(Matrix::<f64>::zero(3, 3).det(), 0.0);
+    }
+
+    #[test]
+    fn rank1_update_matches_outer() {
+        let a = Matrix::from_fn(3, 2, |i, j| (i + 2 * j) as f64);
+        let x = Vector::new(vec![1.0, -2.0, 0.5]);
+        let y = Vector::new(vec![3.0, 4.0]);
+        let outer = Matrix::from_vec(x.into_vec(), 3, 1)
+            .matmul(&Matrix::from_vec(y.into_vec(), 1, 2));
+
+        let mut b = a.transpose();
+        b.rank1_update(0.5, &Vector::new(y.into_vec()), &x);
+        let mut c = a.clone();
+        c.rank1_update(-2.0, &x, &y);
+
+        assert!(b.transpose().approx_eq(&a.zip_elems(&outer, |p, q| p + 0.5 * q), 1e-12));
+        assert!(c.approx_eq(&a.zip_elems(&outer, |p, q| p - 2.0 * q), 1e-12));
+    }
+
+    #[test]
+    fn solve_tridiagonal_matches_dense() {
+        let sub = Vector::new(vec![1.0, -1.0, 2.0, 0.5]);
+        let diag = Vector::new(vec![4.0, 5.0, -6.0, 7.0, 3.0]);
+        let sup = Vector::new(vec![2.0, 1.0, 1.5, -1.0]);
+        let b = Vector::new(vec![1.0, 2.0, 3.0, 4.0, 5.0]);
+
+        let a = Matrix::from_fn(5, 5, |i, j| match j as isize - i as isize {
+            0 => diag.get_data()[i],
+            1 => sup.get_data()[i],
+            -1 => sub.get_data()[j],
+            _ => 0.0,
+        });
+        let x = Matrix::solve_tridiagonal(&sub, &diag, &sup, &b);
+        let y = a.solve(&b).unwrap();
+
+        assert!(x.iter().zip(y.iter()).all(|(p, q)| (p - q).abs() < 1e-12));
+        assert!(a.mul_slice(x.as_slice()).iter().zip(b.iter())
+                .all(|(p, q)| (p - q).abs() < 1e-12));
+    }
+
+    #[test]
+    fn det_triangular_matches_det() {
+        let u = Matrix::from_vec(vec![2.0, 7.0, -1.0,
+                                      0.0, -3.0, 4.0,
+                                      0.0, 0.0, 0.5], 3, 3);
+
+        assert!((u.det_triangular() - u.det()).abs() < 1e-12);
+        assert!((u.transpose().det_triangular() + 3.0).abs() < 1e-12);
+    }
+
+    #[test]
+    #[should_panic(expected = "Matrix is not triangular")]
+    fn det_triangular_rejects_full() {
+        Matrix::from_vec(vec![1.0, 2.0, 3.0, 4.0], 2, 2).det_triangular();
+    }
+
+    #[test]
+    fn gram_matches_transpose_product() {
+        let a = Matrix::from_fn(4, 3, |i, j| (i as f64 - 1.5) * (j as f64 + 0.5) + (i * j) as f64);
+        let g = a.gram();
+
+        assert!(g.approx_eq(&a.transpose().matmul(&a), 1e-12));
+        assert_eq!(g, g.symmetrize());
+    }
+
+    #[test]
+    fn degenerate_shapes() {
+        let shapes = [Matrix::<f64>::empty(), Matrix::zero(0, 3), Matrix::unit(3, 0)];
+        for m in shapes.iter() {
+            let (r, c) = m.get_shape();
+            assert!(m.is_empty());
+            assert_eq!(m.get_size(), 0);
+            assert_eq!(m.get(0, 0), None);
+            assert_eq!(format!("{}", m), "");
+            assert_eq!(format!("{}", SciDisplay(m)), "");
+            assert_eq!(m.to_rows().len(), r);
+            assert_eq!(m.row_chunks(2).count(), (r + 1) / 2);
+            assert_eq!(m.transpose().get_shape(), (c, r));
+            assert_eq!(m.sum(), 0.0);
+            assert_eq!(m.frobenius_norm(), 0.0);
+            assert_eq!(m.cumsum_axis(Axis::Row).get_shape(), (r, c));
+            assert_eq!(m.cumsum_axis(Axis::Column).get_shape(), (r, c));
+            assert_eq!(m.matmul(&m.transpose()).get_shape(), (r, r));
+            assert!(m.approx_eq(&m.clone(), 0.0));
+        }
+        assert_eq!(Matrix::<f64>::empty().get_shape(), (0, 0));
+        assert_eq!(Matrix::<f64>::from_rows(Vec::new()), Matrix::empty());
+    }
+
+    #[test]
+    fn get_unchecked_in_bounds() {
+        let mut m = col_major(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], 3, 2);
+        unsafe {
+            assert_eq!(m.get_unchecked(2, 1), 6.0);
+            *m.get_unchecked_mut(0, 1) = 40.0;
+        }
+        assert_eq!(m.get(0, 1), Some(40.0));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "Index out of bounds")]
+    fn get_unchecked_debug_assert() {
+        // (0, 3) maps to offset 3, which is inside the buffer
+        let m = Matrix::from_vec(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], 2, 3);
+        unsafe { m.get_unchecked(0, 3); }
+    }
+
+    #[test]
+    fn recip_elements() {
+        let m = Matrix::from_vec(vec![2.0, -4.0, 0.5, 0.0], 2, 2);
+        let r = m.recip();
+
+        assert_eq!(&r.get_data()[..3], &[0.5, -0.25, 2.0]);
+        assert_eq!(r.get(1, 1), Some(f64::INFINITY));
+    }
+
+    #[test]
+    fn weighted_row_mean_uniform_and_skewed() {
+        let m = Matrix::from_fn(3, 4, |i, j| (i * i + 2 * j) as f64);
+
+        let uniform = m.weighted_row_mean(&Vector::new(vec![2.0; 3]));
+        assert!(uniform.iter().zip(m.col_means().iter()).all(|(a, b)| (a - b).abs() < 1e-12));
+
+        let last = m.weighted_row_mean(&Vector::new(vec![0.0, 0.0, 5.0]));
+        assert_eq!(last.into_vec(), m.to_rows()[2]);
+    }
+
+    #[test]
+    fn eig_symmetric_decomposes() {
+        let a = Matrix::from_vec(vec![4.0, 1.0, -2.0, 0.5,
+                                      1.0, 3.0, 0.0, 1.0,
+                                      -2.0, 0.0, 5.0, 2.0,
+                                      0.5, 1.0, 2.0, -1.0], 4, 4);
+        let (vals, v) = a.eig_symmetric();
+        let lambda = Matrix::diag(&vals.into_vec(), 4, 4);
+
+        assert!(vals.get_data().windows(2).all(|w| w[0] <= w[1]));
+        assert!(v.gram().approx_eq(&Matrix::eye(4), 1e-12));
+        assert!(a.matmul(&v).approx_eq(&v.matmul(&lambda), 1e-10));
+    }
+
+    #[test]
+    fn generalized_eig_known_pair() {
+        // A v = lambda B v with diagonal A and B has eigenvalues a_ii / b_ii
+        let a = Matrix::diag(&vec![6.0, -2.0, 3.0], 3, 3);
+        let b = Matrix::diag(&vec![2.0, 4.0, 1.0], 3, 3);
+        let (vals, _) = a.generalized_eig_symmetric(&b);
+        assert!(vals.iter().zip([-0.5, 3.0, 3.0].iter()).all(|(x, y)| (x - y).abs() < 1e-12));
+
+        let a = Matrix::from_vec(vec![2.0, 1.0, 1.0, 3.0], 2, 2);
+        let b = Matrix::from_vec(vec![4.0, 1.0, 1.0, 2.0], 2, 2);
+        let (vals, v) = a.generalized_eig_symmetric(&b);
+        let lambda = Matrix::diag(&vals.into_vec(), 2, 2);
+
+        assert!(a.matmul(&v).approx_eq(&b.matmul(&v).matmul(&lambda), 1e-12));
+        assert!(v.transpose().matmul(&b).matmul(&v).approx_eq(&Matrix::eye(2), 1e-12));
+        // det(A - lambda B) = 7 lambda^2 - 14 lambda + 5
+        let r = (14.0f64 * 14.0 - 4.0 * 7.0 * 5.0).sqrt();
+        assert!((vals.get_data()[0] - (14.0 - r) / 14.0).abs() < 1e-12);
+        assert!((vals.get_data()[1] - (14.0 + r) / 14.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn cholesky_factor() {
+        let a = Matrix::from_vec(vec![4.0, 2.0, -2.0, 2.0, 10.0, 2.0, -2.0, 2.0, 6.0], 3, 3);
+        let l = a.cholesky().unwrap();
+
+        assert!(l.matmul(&l.transpose()).approx_eq(&a, 1e-12));
+        assert_eq!(l.get(0, 2), Some(0.0));
+        assert!(Matrix::from_vec(vec![1.0, 2.0, 2.0, 1.0], 2, 2).cholesky().is_none());
+    }
+
+    #[test]
+    fn apply_indexed_masks_triangle() {
+        let m = Matrix::from_fn(3, 4, |i, j| (1 + i * 4 + j) as f64);
+        let masked = m.apply_indexed(|i, j, x| if j > i { 0.0 } else { x });
+
+        assert_eq!(masked, m.tril());
+        assert_eq!(m.tril().get_data(), &vec![1.0, 0.0, 0.0, 0.0,
+                                              5.0, 6.0, 0.0, 0.0,
+                                              9.0, 10.0, 11.0, 0.0]);
+        assert_eq!(m.apply_indexed(|i, j, x| x - (i * 4 + j) as f64), Matrix::unit(3, 4));
+    }
+
+    #[test]
+    fn fresh_matrices_are_contiguous() {
+        let m = Matrix::from_fn(3, 5, |i, j| (i + j) as f64);
+
+        assert!(m.is_contiguous());
+        assert!(m.transpose().is_contiguous());
+        assert!(col_major(vec![0.0; 6], 2, 3).is_contiguous());
+        assert!(m.step_view(2, 2).is_contiguous());
+        assert!(Matrix::<f64>::empty().is_contiguous());
+    }
+
+    #[test]
+    fn rescale_to_sum_target() {
+        let m = Matrix::from_vec(vec![1.0, 3.0, 2.0, 4.0], 2, 2);
+
+        assert_eq!(m.rescale_to_sum(1.0).get_data(), &vec![0.1, 0.3, 0.2, 0.4]);
+        assert!((m.rescale_to_sum(-7.5).sum() + 7.5).abs() < 1e-12);
+    }
+
+    #[test]
+    #[should_panic(expected = "Sum is zero")]
+    fn rescale_to_sum_zero() {
+        Matrix::from_vec(vec![1.0, -1.0], 1, 2).rescale_to_sum(1.0);
+    }
+
+    #[test]
+    fn logical_not_inverts_mask() {
+        let a = Matrix::from_vec(vec![1.0, 5.0, -2.0, 3.0], 2, 2);
+        let mask = a.greater_than(&Matrix::from_vec(vec![0.0, 6.0, -3.0, 3.0], 2, 2));
+
+        assert_eq!(mask.logical_not().get_data(), &vec![0.0, 1.0, 0.0, 1.0]);
+        assert_eq!(mask.logical_not().logical_not(), mask);
+        assert_eq!(a.logical_not(), Matrix::zero(2, 2));
+    }
+
+    #[test]
+    fn histogram_counts() {
+        let m = Matrix::from_vec(vec![0.0, 0.5, 1.0, 1.9, 2.0, 3.99, 4.0, -1.0, 7.0, f64::NAN], 2, 5);
+
+        let h = m.histogram(4, (0.0, 4.0), false);
+        assert_eq!(h, vec![2, 2, 1, 2]);
+        assert_eq!(h.iter().sum::<usize>(), 7);
+        assert_eq!(m.histogram(4, (0.0, 4.0), true), vec![3, 2, 1, 3]);
+        assert_eq!(m.histogram(1, (0.0, 4.0), false), vec![7]);
+    }
+
+    #[test]
+    fn percentile_interpolates() {
+        let m = Matrix::from_vec(vec![7.0, 1.0, 3.0, 15.0, 9.0, 5.0], 2, 3);
+
+        assert_eq!(m.percentile(0.0), 1.0);
+        assert_eq!(m.percentile(100.0), 15.0);
+        assert_eq!(m.percentile(50.0), 6.0);
+        assert_eq!(m.percentile(20.0), 3.0);
+        assert_eq!(m.percentile(90.0), 12.0);
+        assert_eq!(Matrix::from_vec(vec![4.0, 2.0, 8.0], 3, 1).percentile(50.0), 4.0);
+
+        let nan = Matrix::from_vec(vec![f64::NAN, 4.0, 2.0, f64::NAN, 8.0, 6.0], 2, 3);
+        assert_eq!(nan.percentile(0.0), 2.0);
+        assert_eq!(nan.percentile(50.0), 5.0);
+        assert_eq!(nan.percentile(100.0), 8.0);
+    }
+
+    #[test]
+    fn sym_matrix_round_trip() {
+        let a = Matrix::from_fn(4, 4, |i, j| (i * j) as f64 + (i + j) as f64 * 0.5);
+        let s = SymMatrix::from_dense(&a);
+
+        assert_eq!(s.to_dense(), a);
+        assert_eq!(s.get(1, 3), s.get(3, 1));
+        assert_eq!(s.get(4, 0), None);
+        assert_eq!(s.get_shape(), (4, 4));
+
+        let x = Vector::new(vec![1.0, -2.0, 0.5, 3.0]);
+        assert_eq!(s.matvec(&x).into_vec(), a.matvec(&x).into_vec());
+
+        // Only the lower triangle is read
+        let mut b = a.clone();
+        b.set(0, 3, 100.0);
+        assert_eq!(SymMatrix::from_dense(&b), s);
+    }
+
+    #[test]
+    fn sparse_matvec_matches_dense() {
+        let a = Matrix::from_fn(5, 4, |i, j| if (i + 2 * j) % 3 == 0 { (i + j) as f64 - 2.5 } else { 0.0 });
+        let s = SparseMatrix::from_dense(&a);
+        let x = Vector::new(vec![1.0, -2.0, 0.5, 3.0]);
+
+        assert_eq!(s.nnz(), a.count_nonzero(0.0));
+        assert_eq!(s.to_dense(), a);
+        assert_eq!(s.get(2, 2), a.get(2, 2));
+        assert_eq!(s.get(1, 0), Some(0.0));
+        assert_eq!(s.matvec(&x).into_vec(), a.matvec(&x).into_vec());
+    }
+
+    #[test]
+    fn cg_solves_spd() {
+        let a = Matrix::from_vec(vec![4.0, 1.0, 0.0, 1.0,
+                                      1.0, 5.0, 2.0, 0.0,
+                                      0.0, 2.0, 6.0, 1.0,
+                                      1.0, 0.0, 1.0, 3.0], 4, 4);
+        let b = Vector::new(vec![1.0, 2.0, 3.0, 4.0]);
+        let x = a.cg(&b, 50, 1e-10).unwrap();
+
+        let r: Vec<f64> = a.matvec(&x).iter().zip(b.iter()).map(|(p, q)| p - q).collect();
+        assert!(dot(&r, &r).sqrt() <= 1e-10);
+        assert!(a.cg(&b, 1, 1e-10).is_none());
+    }
+
+    #[test]
+    fn jacobi_converges_when_dominant() {
+        let a = Matrix::from_vec(vec![10.0, -1.0, 2.0,
+                                      -1.0, 11.0, -1.0,
+                                      2.0, -1.0, 10.0], 3, 3);
+        let b = Vector::new(vec![6.0, 25.0, -11.0]);
+        let x = a.jacobi(&b, 100, 1e-10).unwrap();
+
+        assert!(x.iter().zip(a.solve(&b).unwrap().iter()).all(|(p, q)| (p - q).abs() < 1e-10));
+
+        let bad = Matrix::from_vec(vec![1.0, 2.0, 3.0, 1.0], 2, 2);
+        assert!(bad.jacobi(&Vector::new(vec![1.0, 1.0]), 1000, 1e-10).is_none());
+    }
+
+    #[test]
+    fn gauss_seidel_beats_jacobi() {
+        let a = Matrix::from_vec(vec![4.0, -1.0, 1.0,
+                                      -1.0, 4.0, -2.0,
+                                      1.0, -2.0, 4.0], 3, 3);
+        let b = Vector::new(vec![12.0, -1.0, 5.0]);
+
+        let x = a.gauss_seidel(&b, 100, 1e-10).unwrap();
+        assert!(x.iter().zip(a.solve(&b).unwrap().iter()).all(|(p, q)| (p - q).abs() < 1e-10));
+
+        let (_, gs) = a.relax(&b, 100, 1e-10, |x| a.gauss_seidel_sweep(&b, x)).unwrap();
+        let (_, jac) = a.relax(&b, 100, 1e-10, |x| a.jacobi_sweep(&b, x)).unwrap();
+        assert!(gs < jac);
+    }
+
+    #[test]
+    fn fractional_pow_square_root() {
+        let a = Matrix::from_vec(vec![4.0, 1.0, 0.5,
+                                      1.0, 3.0, 0.0,
+                                      0.5, 0.0, 2.0], 3, 3);
+        let h = a.fractional_pow(0.5);
+
+        assert!(h.matmul(&h).approx_eq(&a, 1e-12));
+        assert!(a.fractional_pow(-1.0).approx_eq(&a.inverse().unwrap(), 1e-12));
+        assert!(a.fractional_pow(1.0).approx_eq(&a, 1e-12));
+    }
+
+    #[test]
+    fn sqrtm_squares_back() {
+        let b = Matrix::from_fn(4, 3, |i, j| ((i * 5 + j * 3) % 7) as f64 - 2.0);
+        let mut a = b.gram();
+        a.add_to_diagonal(0.1);
+        let s = a.sqrtm().unwrap();
+
+        assert!(s.matmul(&s).approx_eq(&a, 1e-10));
+        assert!(s.approx_eq(&s.transpose(), 1e-12));
+        assert!(Matrix::from_vec(vec![1.0, 2.0, 2.0, 1.0], 2, 2).sqrtm().is_none());
+        assert!(Matrix::from_vec(vec![2.0, 1.0, 0.0, 2.0], 2, 2).sqrtm().is_none());
+    }
+
+    #[test]
+    fn expm_inverts_logm() {
+        let a = Matrix::from_vec(vec![5.0, 1.0, -1.0,
+                                      1.0, 4.0, 0.5,
+                                      -1.0, 0.5, 3.0], 3, 3);
+        let l = a.logm().unwrap();
+
+        assert!(l.expm().approx_eq_rel(&a, 1e-12));
+        assert!(Matrix::from_vec(vec![0.0, 1.0, 1.0, 0.0], 2, 2).logm().is_none());
+    }
+
+    #[test]
+    fn expm_known_values() {
+        let d = Matrix::diag(&vec![1.0, -2.0], 2, 2).expm();
+        assert!(d.approx_eq(&Matrix::diag(&vec![1f64.exp(), (-2f64).exp()], 2, 2), 1e-14));
+
+        // exp of a rotation generator is a rotation
+        let t = 3.0f64;
+        let r = Matrix::from_vec(vec![0.0, -t, t, 0.0], 2, 2).expm();
+        assert!(r.approx_eq(&Matrix::from_vec(vec![t.cos(), -t.sin(), t.sin(), t.cos()], 2, 2), 1e-13));
+        assert_eq!(Matrix::<f64>::zero(3, 3).expm(), Matrix::eye(3));
+    }
+
+    #[test]
+    fn expm_non_finite() {
+        let inf = Matrix::from_vec(vec![f64::INFINITY, 0.0, 0.0, 1.0], 2, 2).expm();
+        let nan = Matrix::from_vec(vec![1.0, f64::NAN, 0.0, 1.0], 2, 2).expm();
+
+        assert!(inf.get_data().iter().all(|x| x.is_nan()));
+        assert!(nan.get_data().iter().all(|x| x.is_nan()));
+
+        // Squaring the norm of these overflows, but the scaling does not
+        assert_eq!(Matrix::from_vec(vec![f64::MAX], 1, 1).expm().get(0, 0), Some(f64::INFINITY));
+        assert_eq!(Matrix::from_vec(vec![-f64::MAX], 1, 1).expm().get(0, 0), Some(0.0));
+    }
+
+    #[test]
+    fn kron_and_kron_sum() {
+        let a = Matrix::from_vec(vec![1.0, 2.0, 3.0, 4.0], 2, 2);
+        let b = Matrix::from_vec(vec![0.0, 5.0, 6.0, 7.0], 2, 2);
+
+        assert_eq!(a.kron(&Matrix::from_vec(vec![1.0, -1.0], 1, 2)).get_data(),
+                   &vec![1.0, -1.0, 2.0, -2.0, 3.0, -3.0, 4.0, -4.0]);
+
+        let s = a.kron_sum(&b);
+        assert_eq!(s.get_shape(), (4, 4));
+        assert_eq!(s.get_data(), &vec![1.0, 5.0, 2.0, 0.0,
+                                       6.0, 8.0, 0.0, 2.0,
+                                       3.0, 0.0, 4.0, 5.0,
+                                       0.0, 3.0, 6.0, 11.0]);
+        assert_eq!(a.kron_sum(&Matrix::eye(3)).get_shape(), (6, 6));
+    }
+
+    #[test]
+    fn vectorize_round_trip() {
+        let m = Matrix::from_vec(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], 2, 3);
+        let v = m.vectorize();
+
+        assert_eq!(v.get_data(), &vec![1.0, 4.0, 2.0, 5.0, 3.0, 6.0]);
+        assert_eq!(Matrix::from_vec_col(&v, 2, 3), m);
+        assert_eq!(m.transpose().vectorize().into_vec(), m.get_data().clone());
+    }
+
+    #[test]
+    fn solve_sylvester_small() {
+        let a = Matrix::from_vec(vec![3.0, 1.0, 0.0, 2.0], 2, 2);
+        let b = Matrix::from_vec(vec![1.0, -1.0, 0.5, 4.0, 0.0, 1.0, 2.0, 0.0, 5.0], 3, 3);
+        let c = Matrix::from_fn(2, 3, |i, j| (i + 2 * j) as f64 - 1.0);
+        let x = Matrix::solve_sylvester(&a, &b, &c);
+
+        let lhs = a.matmul(&x).zip_elems(&x.matmul(&b), |p, q| p + q);
+        assert_eq!(x.get_shape(), (2, 3));
+        assert!(lhs.approx_eq(&c, 1e-12));
+    }
+
+    #[test]
+    fn topk_rows_indices() {
+        let m = Matrix::from_vec(vec![0.3, 0.9, 0.1, 0.7, 0.5,
+                                      2.0, -1.0, 2.0, 0.0, 3.0], 2, 5);
+        let top = m.topk_rows(3);
+
+        assert_eq!(top.get_shape(), (2, 3));
+        assert_eq!(top.get_data(), &vec![1, 3, 4, 4, 0, 2]);
+        for j in 0..3 {
+            let col = top.get(0, j).unwrap();
+            assert!(m.get(0, col).unwrap() >= 0.5);
+        }
+        assert_eq!(m.topk_rows(0).get_shape(), (2, 0));
+
+        let nan = Matrix::from_vec(vec![f64::NAN, 1.0, f64::NAN, 3.0, 2.0], 1, 5);
+        assert_eq!(nan.topk_rows(5).get_data(), &vec![3, 4, 1, 0, 2]);
+    }
 }
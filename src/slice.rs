@@ -6,7 +6,7 @@ use num::traits::FromPrimitive;
 use std::marker::PhantomData;
 
 //use super::matrix::{Matrix, MatrixSlice, MatrixMutSlice};
-use super::matrix::{Matrix};
+use super::matrix::{Axis, Matrix};
 
 //=============================================================================
 //Matrix Slice
@@ -42,10 +42,11 @@ impl<'a, T> MatrixSlice<'a, T> {
                                                                     nc: usize)
         -> MatrixSlice<'a, T> 
         where T: Float + FromPrimitive {
-            assert!(begin[0] + nr <= nr, "View dimensions exceed matrix
+            assert!(begin[0] + nr <= mat.get_rows(), "View dimensions exceed matrix
                                                                 dimensions");
-            assert!(begin[1] + nc <= nc, "View dimentions exceed matrix
+            assert!(begin[1] + nc <= mat.get_cols(), "View dimentions exceed matrix
                                                                 dimensions");
+            assert!(mat.get_mode() == Axis::Row, "View requires row major storage");
 
             unsafe {
                 MatrixSlice {
@@ -59,6 +60,12 @@ impl<'a, T> MatrixSlice<'a, T> {
             }
         }
 
+    /// Slice over raw row major data
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be valid for reads of `nr` rows of `nc` elements spaced
+    /// `row_stride` apart, for the lifetime `'a`.
     pub unsafe fn from_raw_parts(ptr: *const T, nr: usize, nc: usize, 
                                  row_stride: usize) -> MatrixSlice<'a, T> {
         MatrixSlice {
@@ -70,6 +77,24 @@ impl<'a, T> MatrixSlice<'a, T> {
         }
     }
 
+    /// Element at the given position of the view, honouring the row stride
+    fn elem(&self, rid: usize, cid: usize) -> T
+        where T: Copy {
+            assert!(rid < self.nr && cid < self.nc, "Index out of bounds");
+            unsafe { *self.pt.add(rid * self.rs + cid) }
+        }
+
+    /// Matrix product of two views
+    pub fn matmul(&self, rhs: &MatrixSlice<T>) -> Matrix<'a, T> 
+        where T: Float {
+            assert!(self.get_cols() == rhs.get_rows(), "Dimension mismatch");
+            Matrix::from_fn(self.get_rows(), rhs.get_cols(), |i, j| {
+                (0..self.get_cols()).fold(T::zero(), |acc, k| {
+                    acc + self.elem(i, k) * rhs.elem(k, j)
+                })
+            })
+        }
+
 }
 //=============================================================================
 //Mutable matrix slice
@@ -103,10 +128,11 @@ impl<'a, T> MatrixMutSlice<'a, T> {
     pub fn from_matrix(mat: &'a mut Matrix<T>, begin: [usize; 2], 
                        nr: usize, nc: usize) -> MatrixMutSlice<'a, T> 
         where T: Float + FromPrimitive {
-            assert!(begin[0] + nr <= nr, "View dimensions exceed matrix
+            assert!(begin[0] + nr <= mat.get_rows(), "View dimensions exceed matrix
                                 dimensions");
-            assert!(begin[1] + nc <= nc, "View dimentions exceed matrix
+            assert!(begin[1] + nc <= mat.get_cols(), "View dimentions exceed matrix
                                 dimensions");
+            assert!(mat.get_mode() == Axis::Row, "View requires row major storage");
             let ncols = mat.clone().get_cols();
             unsafe {
                 MatrixMutSlice {
@@ -120,6 +146,13 @@ impl<'a, T> MatrixMutSlice<'a, T> {
             }
         }
 
+    /// Mutable slice over raw row major data
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be valid for reads and writes of `nr` rows of `nc`
+    /// elements spaced `row_stride` apart, for the lifetime `'a`, and must
+    /// not be aliased.
     pub unsafe fn from_raw_parts(ptr: *mut T, nr: usize, nc: usize, row_stride: usize) -> MatrixMutSlice<'a, T> {
         MatrixMutSlice {
             pt: ptr,
@@ -179,3 +212,20 @@ pub struct ColMut<'a, T> {
     col: MatrixMutSlice<'a, T>,
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slice_matmul_honours_stride() {
+        let m = Matrix::from_fn(4, 4, |i, j| (i * 4 + j) as f64 - 5.0);
+        let a = MatrixSlice::from_matrix(&m, [0, 1], 2, 2);
+        let b = MatrixSlice::from_matrix(&m, [2, 2], 2, 2);
+
+        let sub = |r: usize, c: usize| Matrix::from_fn(2, 2, |i, j| {
+            m.get(r + i, c + j).unwrap()
+        });
+        assert_eq!(a.matmul(&b), sub(0, 1).matmul(&sub(2, 2)));
+    }
+}
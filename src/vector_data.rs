@@ -4,16 +4,18 @@ use std::slice::IterMut;
 
 use std::vec::IntoIter;
 
+use std::cmp::Ordering;
+
 use num::Float;
 
 // Vector and Vector storage structure
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct Vector<T: Float> {
+pub struct Vector<T> {
     pub data: Vec<T>,
 }
 
 
-impl<T: Float> Vector<T> {
+impl<T: Copy> Vector<T> {
     /// Vector vector: constructor for Vector vector: Requires vector data
     pub fn new(data: Vec<T>) -> Vector<T> {
         let _data = data.into();
@@ -54,11 +56,19 @@ impl<T: Float> Vector<T> {
     }
 
     /// Safe mutable pointer to the element without bound checking
+    ///
+    /// # Safety
+    ///
+    /// `idx` must be less than the vector length.
     pub unsafe fn uget(&self, idx: usize) -> &T {
         self.data.get_unchecked(idx)
     }
 
     /// Mutable reference pointer to the element
+    ///
+    /// # Safety
+    ///
+    /// `idx` must be less than the vector length.
     pub unsafe fn uget_mut(&mut self, idx: usize) -> &mut T {
         self.data.get_unchecked_mut(idx)
     }
@@ -132,12 +142,65 @@ impl<T: Float> Vector<T> {
         }
     }
 
-    pub fn argsort(&self) -> usize {
-        let v = self.clone();
-        let m = self.clone().into_iter().fold(T::min_value(), |x,y| x.max(y));
-        v.into_iter().position(|x| x == m).unwrap()
+    /// Indices that would sort the data in ascending order. The sort is
+    /// stable and NaN sorts after every other value.
+    pub fn argsort(&self) -> Vec<usize> {
+        let mut idx: Vec<usize> = (0..self.get_size()).collect();
+        idx.sort_by(|&a, &b| total_cmp(self.data[a], self.data[b]));
+        idx
     }
 
+    /// Index of the maximum element, the first one on ties. NaN elements
+    /// are skipped; `None` if there is no other element.
+    pub fn argmax(&self) -> Option<usize> {
+        let mut best: Option<usize> = None;
+        for (i, &x) in self.data.iter().enumerate() {
+            if x.is_nan() {
+                continue;
+            }
+            match best {
+                Some(b) if self.data[b] >= x => {},
+                _ => best = Some(i),
+            }
+        }
+        best
+    }
+
+}
+
+
+/// Total order for sorting floats: the usual order, with every NaN equal
+/// to each other and greater than any other value
+pub(crate) fn total_cmp<T: Float>(a: T, b: T) -> Ordering {
+    match (a.is_nan(), b.is_nan()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Greater,
+        (false, true) => Ordering::Less,
+        (false, false) => a.partial_cmp(&b).unwrap(),
+    }
 }
 
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn argsort_and_argmax() {
+        let v = Vector::new(vec![3.0, 1.0, 2.0]);
+
+        assert_eq!(v.argsort(), vec![1, 2, 0]);
+        assert_eq!(v.argmax(), Some(0));
+    }
+
+    #[test]
+    fn argsort_and_argmax_with_nan() {
+        let v = Vector::new(vec![2.0, f64::NAN, -1.0, f64::INFINITY, 2.0]);
+
+        assert_eq!(v.argsort(), vec![2, 0, 4, 3, 1]);
+        assert_eq!(v.argmax(), Some(3));
+        assert_eq!(Vector::new(vec![f64::NEG_INFINITY, -5.0, -5.0]).argmax(), Some(1));
+        assert_eq!(Vector::new(vec![f64::NAN, f64::NAN]).argmax(), None);
+        assert_eq!(Vector::<f64>::new(Vec::new()).argmax(), None);
+    }
+}